@@ -0,0 +1,376 @@
+use std::collections::BTreeSet;
+
+use async_trait::async_trait;
+use itertools::Itertools;
+use log::{error, info, warn};
+use rocket::data::{Data, Limits, ToByteUnit};
+use rocket::http::{ContentType, RawStr, Status};
+use rocket::outcome::Outcome::{Error, Forward, Success};
+use rocket::request::{FromRequest, Outcome};
+use rocket::{get, post, Request, State};
+use serde::Deserialize;
+#[cfg(feature = "github")]
+use serde_yaml::Value;
+
+use crate::r#impl::config::Config;
+#[cfg(feature = "github")]
+use crate::r#impl::github::GithubClient;
+use crate::r#impl::storage::ProductsConfig;
+
+/// Request guard for the admin routes. Requires the configured admin token to be sent as a
+/// bearer token in the `Authorization` header. It is not accepted as a query parameter, as URLs
+/// end up in request logs.
+/// If no admin token is configured, the admin routes are not available.
+pub struct AdminAuth;
+
+#[async_trait]
+impl<'r> FromRequest<'r> for AdminAuth {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let Some(expected) = request
+            .rocket()
+            .state::<Config>()
+            .and_then(Config::admin_token)
+        else {
+            return Forward(Status::NotFound);
+        };
+        let given = request
+            .headers()
+            .get_one("Authorization")
+            .and_then(|v| v.strip_prefix("Bearer "));
+        match given {
+            Some(given) if constant_time_eq(given.as_bytes(), expected.as_bytes()) => {
+                Success(AdminAuth)
+            }
+            _ => Error((Status::Unauthorized, ())),
+        }
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Body of a refresh request. Either lists the products to refresh, or is an S3 event
+/// notification, in which case the products are derived from the keys of the objects.
+/// An empty body refreshes everything.
+#[derive(Debug, Default, Deserialize)]
+struct RefreshRequest {
+    #[serde(default)]
+    product: Option<String>,
+    #[serde(default)]
+    products: Vec<String>,
+    #[serde(default, rename = "Records")]
+    records: Vec<S3EventRecord>,
+}
+
+#[derive(Debug, Deserialize)]
+struct S3EventRecord {
+    s3: S3EventEntity,
+}
+
+#[derive(Debug, Deserialize)]
+struct S3EventEntity {
+    object: S3EventObject,
+}
+
+#[derive(Debug, Deserialize)]
+struct S3EventObject {
+    key: String,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum RefreshScope {
+    Global,
+    Products(BTreeSet<String>),
+}
+
+impl RefreshRequest {
+    /// Parses the JSON body of a refresh request. An empty body is an empty request.
+    fn parse(body: &str) -> serde_json::Result<Self> {
+        if body.trim().is_empty() {
+            Ok(Default::default())
+        } else {
+            serde_json::from_str(body)
+        }
+    }
+
+    /// Returns the products to refresh, by their keys in the configuration, if it could be
+    /// loaded. Products that are not in the configuration are kept as given. Refreshes
+    /// everything if an object of an S3 event does not belong to a product.
+    fn scope(self, products_config: Option<&ProductsConfig>) -> RefreshScope {
+        let mut products: BTreeSet<String> = self
+            .product
+            .into_iter()
+            .chain(self.products)
+            .map(|product| {
+                products_config
+                    .and_then(|config| config.find_product(&product))
+                    .map_or(product.clone(), |(key, _)| key.to_string())
+            })
+            .collect();
+        for record in self.records {
+            let key = RawStr::new(&record.s3.object.key).url_decode_lossy();
+            // E.g. products.yml, or files that are not in the directory of a product.
+            let Some(keys) = products_config.and_then(|config| config.products_of_object(&key))
+            else {
+                return RefreshScope::Global;
+            };
+            products.extend(keys.into_iter().map(str::to_string));
+        }
+        if products.is_empty() {
+            RefreshScope::Global
+        } else {
+            RefreshScope::Products(products)
+        }
+    }
+}
+
+/// Name of the Rocket limit for the body of refresh requests, e.g.
+/// `limits = { admin-refresh = "4MiB" }` in `Rocket.toml` or `ROCKET_LIMITS`.
+const REFRESH_LIMIT_NAME: &str = "admin-refresh";
+
+/// Drops cached data and immediately loads the product configuration, included product files
+/// and bucket listings again.
+/// If products are given, only data specific to these products (e.g. GitHub releases) is dropped.
+/// The body is limited to 1 MiB, enough for batched S3 event notifications, unless the
+/// `admin-refresh` limit is configured.
+#[post("/_admin/refresh", data = "<body>")]
+pub async fn post_admin_refresh(
+    _auth: AdminAuth,
+    config: &State<Config>,
+    limits: &Limits,
+    body: Data<'_>,
+) -> (Status, String) {
+    let limit = limits.get(REFRESH_LIMIT_NAME).unwrap_or(1.mebibytes());
+    let body = match body.open(limit).into_string().await {
+        Ok(body) if body.is_complete() => body.into_inner(),
+        Ok(_) => {
+            return (
+                Status::PayloadTooLarge,
+                format!(
+                    "Request body exceeds the {} limit of {}",
+                    REFRESH_LIMIT_NAME, limit
+                ),
+            )
+        }
+        Err(err) => return (Status::BadRequest, format!("Invalid request: {}", err)),
+    };
+    let request = match RefreshRequest::parse(&body) {
+        Ok(request) => request,
+        Err(err) => return (Status::BadRequest, format!("Invalid request: {}", err)),
+    };
+    // Objects are mapped to products with the configuration before the refresh, which knows
+    // the included files that were changed.
+    let current_config = match config.storage().get_cached_config().await {
+        Ok(current_config) => Some(current_config),
+        Err(err) => {
+            warn!("Failed to get products config: {}", err);
+            None
+        }
+    };
+    let scope = request.scope(current_config.as_ref());
+    let refresh_products = match &scope {
+        RefreshScope::Global => None,
+        RefreshScope::Products(products) => Some(products.iter().cloned().collect::<Vec<_>>()),
+    };
+
+    #[allow(unused)] // github feature
+    let products_config = match config.storage().refresh(refresh_products.as_deref()).await {
+        Ok(products_config) => products_config,
+        Err(err) => {
+            error!("Failed to refresh products config: {}", err);
+            return (
+                Status::BadGateway,
+                format!("Failed to refresh products config: {}", err),
+            );
+        }
+    };
+
+    match scope {
+        RefreshScope::Global => {
+            #[cfg(feature = "github")]
+            GithubClient::invalidate_releases(None).await;
+            info!("Refreshed all cached data.");
+            (Status::Ok, "Refreshed all cached data.".to_string())
+        }
+        RefreshScope::Products(products) => {
+            #[cfg(feature = "github")]
+            for product in &products {
+                if let Some(Value::String(project)) = products_config
+                    .find_product(product)
+                    .and_then(|(_, p)| p.settings.get("github"))
+                {
+                    GithubClient::invalidate_releases(Some(project)).await;
+                }
+            }
+            let products = products.iter().join(", ");
+            info!("Refreshed cached data for products: {}", products);
+            (
+                Status::Ok,
+                format!("Refreshed cached data for products: {}", products),
+            )
+        }
+    }
+}
+
+/// Reports objects that are missing on some endpoints or differ in size or ETag between them,
+/// as JSON. If a prefix is given, only objects with keys starting with it are compared.
+/// Uses cached listings, if available; refresh first to compare the current state.
+#[get("/_admin/mirror-report?<prefix>")]
+pub async fn get_admin_mirror_report(
+    _auth: AdminAuth,
+    config: &State<Config>,
+    prefix: Option<&str>,
+) -> Result<(ContentType, String), Status> {
+    let report = config
+        .storage()
+        .mirror_report(prefix.unwrap_or_default())
+        .await;
+    serde_json::to_string_pretty(&report)
+        .map(|json| (ContentType::JSON, json))
+        .map_err(|err| {
+            error!("Failed to serialize mirror report: {}", err);
+            Status::InternalServerError
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::r#impl::storage::{Product, RawProductsConfig};
+
+    fn products_config() -> ProductsConfig {
+        let raw: RawProductsConfig = serde_yaml::from_str(
+            r#"
+products:
+  tool:
+    name: Tool
+    aliases: [old-tool]
+    versions: {}
+  skytemple:
+    include: products/skytemple.yml
+"#,
+        )
+        .unwrap();
+        let included: Product = serde_yaml::from_str("name: SkyTemple\nversions: {}").unwrap();
+        raw.into_config(&HashMap::from([(
+            "products/skytemple.yml".to_string(),
+            included,
+        )]))
+    }
+
+    fn scope(body: &str) -> RefreshScope {
+        RefreshRequest::parse(body)
+            .unwrap()
+            .scope(Some(&products_config()))
+    }
+
+    fn products(products: &[&str]) -> RefreshScope {
+        RefreshScope::Products(products.iter().map(|p| p.to_string()).collect())
+    }
+
+    fn s3_event(keys: &[&str]) -> String {
+        let records: Vec<_> = keys
+            .iter()
+            .map(|key| {
+                serde_json::json!({
+                    "eventName": "ObjectCreated:Put",
+                    "s3": {
+                        "bucket": { "name": "releases" },
+                        "object": { "key": key, "size": 1024 }
+                    }
+                })
+            })
+            .collect();
+        serde_json::json!({ "Records": records }).to_string()
+    }
+
+    #[test]
+    fn empty_body_refreshes_everything() {
+        assert_eq!(scope(""), RefreshScope::Global);
+        assert_eq!(scope(" \n"), RefreshScope::Global);
+        assert_eq!(scope("{}"), RefreshScope::Global);
+        assert_eq!(scope(r#"{"products": []}"#), RefreshScope::Global);
+    }
+
+    #[test]
+    fn explicit_products() {
+        assert_eq!(scope(r#"{"product": "tool"}"#), products(&["tool"]));
+        assert_eq!(
+            scope(r#"{"products": ["tool", "skytemple", "tool"]}"#),
+            products(&["skytemple", "tool"])
+        );
+        assert_eq!(
+            scope(r#"{"product": "tool", "products": ["skytemple"]}"#),
+            products(&["skytemple", "tool"])
+        );
+    }
+
+    #[test]
+    fn explicit_products_by_alias_or_case() {
+        assert_eq!(
+            scope(r#"{"products": ["old-tool", "SkyTemple"]}"#),
+            products(&["skytemple", "tool"])
+        );
+        // Products that are not configured (yet) are kept as given.
+        assert_eq!(scope(r#"{"product": "new"}"#), products(&["new"]));
+    }
+
+    #[test]
+    fn s3_event_keys() {
+        assert_eq!(
+            scope(&s3_event(&[
+                "tool/1.0.0/tool-win64.exe",
+                "tool/1.0.0/SHA256SUMS",
+                "old-tool/0.1.0/tool.exe",
+                "SkyTemple/1.0.0/skytemple%20setup.exe",
+            ])),
+            products(&["skytemple", "tool"])
+        );
+    }
+
+    #[test]
+    fn s3_event_for_include_path() {
+        assert_eq!(
+            scope(&s3_event(&["products/skytemple.yml"])),
+            products(&["skytemple"])
+        );
+        assert_eq!(
+            scope(&s3_event(&["products%2Fskytemple.yml"])),
+            products(&["skytemple"])
+        );
+    }
+
+    #[test]
+    fn s3_event_outside_of_products_refreshes_everything() {
+        assert_eq!(
+            scope(&s3_event(&["tool/1.0.0/tool.exe", "products.yml"])),
+            RefreshScope::Global
+        );
+        assert_eq!(
+            scope(&s3_event(&["tool/1.0.0/tool.exe", "products/other.yml"])),
+            RefreshScope::Global
+        );
+        assert_eq!(scope(&s3_event(&["assets/logo.png"])), RefreshScope::Global);
+        assert_eq!(scope(&s3_event(&[])), RefreshScope::Global);
+    }
+
+    #[test]
+    fn s3_event_without_config_refreshes_everything() {
+        let request = RefreshRequest::parse(&s3_event(&["tool/1.0.0/tool.exe"])).unwrap();
+        assert_eq!(request.scope(None), RefreshScope::Global);
+        let request = RefreshRequest::parse(r#"{"product": "tool"}"#).unwrap();
+        assert_eq!(request.scope(None), products(&["tool"]));
+    }
+
+    #[test]
+    fn invalid_body_fails() {
+        assert!(RefreshRequest::parse("tool").is_err());
+        assert!(RefreshRequest::parse(r#"{"products": "tool"}"#).is_err());
+        assert!(RefreshRequest::parse(r#"{"Records": [{"s3": {}}]}"#).is_err());
+    }
+}
@@ -3,10 +3,11 @@ use std::io::Cursor;
 
 use async_trait::async_trait;
 use indexmap::IndexMap;
+use itertools::Itertools;
 use rocket::get;
 use rocket::http::uri::Host;
 use rocket::http::{ContentType, Status};
-use rocket::response::Responder;
+use rocket::response::{Redirect, Responder};
 use rocket::{Request, Response, State};
 use serde_yaml::{Mapping, Value};
 
@@ -24,6 +25,14 @@ pub const FLATHUB_STABLE_ARTIFACT_KEY: &str = "flathub";
 pub const FLATHUB_BETA_ARTIFACT_KEY: &str = "flathub_beta";
 const FLATHUB_URL: &str = "https://dl.flathub.org/repo/";
 const FLATHUB_RUNTIME_REPO: &str = "https://dl.flathub.org/repo/flathub.flatpakrepo";
+/// Keys of the setting of custom Flatpak repos and whether they are booleans (or strings).
+const CUSTOM_SETTING_KEYS: [(&str, bool); 5] = [
+    ("repo", false),
+    ("repo_suggested_name", false),
+    ("repo_gpg_verify", true),
+    ("repo_branch", false),
+    ("package", false),
+];
 
 #[derive(Debug, Clone)]
 pub struct FlatpakRepo {
@@ -126,6 +135,38 @@ impl ArtifactType for FlatpakArtifactType {
             runtime_repo: Cow::Borrowed(FLATHUB_RUNTIME_REPO),
         }))
     }
+
+    fn validate_setting(&self, setting: Option<&Value>) -> Result<(), String> {
+        if self.repo.is_some() {
+            return match setting {
+                Some(Value::String(_)) => Ok(()),
+                _ => Err("Expected the Flatpak package ID.".to_string()),
+            };
+        }
+        let Some(Value::Mapping(mapping)) = setting else {
+            return Err(format!(
+                "Expected a mapping with the keys {}.",
+                CUSTOM_SETTING_KEYS.iter().map(|(k, _)| k).join(", ")
+            ));
+        };
+        let invalid = CUSTOM_SETTING_KEYS
+            .iter()
+            .filter(|(key, is_bool)| {
+                !matches!(
+                    (mapping.get(key), is_bool),
+                    (Some(Value::Bool(_)), true) | (Some(Value::String(_)), false)
+                )
+            })
+            .map(|(key, is_bool)| {
+                format!("{} ({})", key, if *is_bool { "boolean" } else { "string" })
+            })
+            .collect::<Vec<_>>();
+        if invalid.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Missing or invalid keys: {}.", invalid.join(", ")))
+        }
+    }
 }
 
 impl FlatpakArtifactType {
@@ -173,24 +214,31 @@ impl FlatpakArtifactType {
         &self,
         config: &State<Config>,
         product: &str,
-    ) -> Result<Flatpakref, Status> {
-        let mut products = get_storage_config(config).await?.products;
-
-        if let Some(product_data) = products.swap_remove(product) {
-            let setting = product_data.settings.get(self.artifact_key);
-            // TODO: Flatpakref doesn't really allow specifying a special commit, so we always serve latest for now.
-            let (repo_info, package_id) = self.get_infos(setting).map_err(|_| Status::NotFound)?;
-            Ok(Flatpakref {
-                name: Cow::Owned(package_id.to_string()),
-                branch: repo_info.branch.clone(),
-                title: Cow::Owned(product_data.name.clone()),
-                url: repo_info.url.clone(),
-                gpg_verify: repo_info.gpg_verify,
-                runtime_repo: Cow::Borrowed(FLATHUB_RUNTIME_REPO),
-            })
-        } else {
-            Err(Status::NotFound)
+        release: &str,
+    ) -> Result<FlatpakrefResponder, Status> {
+        let storage_config = get_storage_config(config).await?;
+        let Some((product_key, product_data)) = storage_config.find_product(product) else {
+            return Err(Status::NotFound);
+        };
+        if product_key != product {
+            // The routes are named after the artifact types.
+            return Ok(FlatpakrefResponder::Redirect(Redirect::permanent(format!(
+                "/{}/{}/{}",
+                self.artifact_key, product_key, release
+            ))));
         }
+
+        let setting = product_data.settings.get(self.artifact_key);
+        // TODO: Flatpakref doesn't really allow specifying a special commit, so we always serve latest for now.
+        let (repo_info, package_id) = self.get_infos(setting).map_err(|_| Status::NotFound)?;
+        Ok(FlatpakrefResponder::Flatpakref(Flatpakref {
+            name: Cow::Owned(package_id.to_string()),
+            branch: repo_info.branch.clone(),
+            title: Cow::Owned(product_data.name.clone()),
+            url: repo_info.url.clone(),
+            gpg_verify: repo_info.gpg_verify,
+            runtime_repo: Cow::Borrowed(FLATHUB_RUNTIME_REPO),
+        }))
     }
 
     fn get_infos<'a>(
@@ -199,12 +247,9 @@ impl FlatpakArtifactType {
     ) -> Result<(Cow<'a, FlatpakRepo>, Cow<'a, str>), ArtifactError> {
         // If we have repo settings set, then we only need the package id from the settings.
         // Otherwise we need all from the settings.
-        if self.repo.is_some() {
+        if let Some(repo) = &self.repo {
             match setting {
-                Some(Value::String(package_id)) => Ok((
-                    Cow::Borrowed(self.repo.as_ref().unwrap()),
-                    package_id.into(),
-                )),
+                Some(Value::String(package_id)) => Ok((Cow::Borrowed(repo), package_id.into())),
                 _ => Err(ArtifactError::MissingSetting),
             }
         } else {
@@ -255,8 +300,8 @@ pub async fn get_flatpakref(
     host: &Host<'_>,
     config: &State<Config>,
     product: &str,
-    #[allow(unused)] release: &str,
-) -> Result<Flatpakref, Status> {
+    release: &str,
+) -> Result<FlatpakrefResponder, Status> {
     if is_release_info(config, host) {
         Err(Status::NotFound)
     } else {
@@ -264,7 +309,7 @@ pub async fn get_flatpakref(
             FLATHUB_STABLE_ARTIFACT_KEY,
             Some(FlatpakRepo::flathub_stable()),
         )
-        .get_flatpakref_impl(config, product)
+        .get_flatpakref_impl(config, product, release)
         .await
     }
 }
@@ -274,13 +319,13 @@ pub async fn get_flatpakref_beta(
     host: &Host<'_>,
     config: &State<Config>,
     product: &str,
-    #[allow(unused)] release: &str,
-) -> Result<Flatpakref, Status> {
+    release: &str,
+) -> Result<FlatpakrefResponder, Status> {
     if is_release_info(config, host) {
         Err(Status::NotFound)
     } else {
         FlatpakArtifactType::new(FLATHUB_BETA_ARTIFACT_KEY, Some(FlatpakRepo::flathub_beta()))
-            .get_flatpakref_impl(config, product)
+            .get_flatpakref_impl(config, product, release)
             .await
     }
 }
@@ -290,17 +335,32 @@ pub async fn get_flatpakref_custom(
     host: &Host<'_>,
     config: &State<Config>,
     product: &str,
-    #[allow(unused)] release: &str,
-) -> Result<Flatpakref, Status> {
+    release: &str,
+) -> Result<FlatpakrefResponder, Status> {
     if is_release_info(config, host) {
         Err(Status::NotFound)
     } else {
         FlatpakArtifactType::new(FLATPAK_CUSTOM_ARTIFACT_KEY, None)
-            .get_flatpakref_impl(config, product)
+            .get_flatpakref_impl(config, product, release)
             .await
     }
 }
 
+pub enum FlatpakrefResponder {
+    Flatpakref(Flatpakref),
+    /// Redirect from an alias to the canonical URL.
+    Redirect(Redirect),
+}
+
+impl<'r, 'o: 'r> Responder<'r, 'o> for FlatpakrefResponder {
+    fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'o> {
+        match self {
+            FlatpakrefResponder::Flatpakref(v) => v.respond_to(request),
+            FlatpakrefResponder::Redirect(v) => v.respond_to(request),
+        }
+    }
+}
+
 pub struct Flatpakref {
     name: Cow<'static, str>,
     branch: Cow<'static, str>,
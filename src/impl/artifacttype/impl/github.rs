@@ -125,4 +125,16 @@ impl ArtifactType for GithubArtifactType {
             _ => Err(ArtifactError::MissingSetting),
         }
     }
+
+    fn validate_setting(&self, setting: Option<&Value>) -> Result<(), String> {
+        match setting
+            .and_then(Value::as_str)
+            .and_then(|v| v.split_once('/'))
+        {
+            Some((org, repo)) if !org.is_empty() && !repo.is_empty() && !repo.contains('/') => {
+                Ok(())
+            }
+            _ => Err("Expected the GitHub repository as 'org/repo'.".to_string()),
+        }
+    }
 }
@@ -58,4 +58,11 @@ impl ArtifactType for PypiArtifactType {
     ) -> Result<NightlyArtifactResponder, ArtifactError> {
         Err(ArtifactError::NotSupported)
     }
+
+    fn validate_setting(&self, setting: Option<&Value>) -> Result<(), String> {
+        match setting {
+            Some(Value::String(_)) => Ok(()),
+            _ => Err("Expected the PyPI project name.".to_string()),
+        }
+    }
 }
@@ -14,10 +14,12 @@ use thiserror::Error;
 
 use crate::r#impl::artifacttype::fallback::FallbackArtifactType;
 use crate::r#impl::artifacttype::r#impl::*;
-use crate::r#impl::config::Endpoints;
 use crate::r#impl::nightly::NightlyConfig;
 use crate::r#impl::release_map::NamedVersion;
-use crate::r#impl::storage::DownloadSpec;
+use crate::r#impl::storage::{
+    expand_download_templates, Checksums, DownloadSpec, MirrorListings, ObjectPresence, Product,
+    Storage, StorageObject,
+};
 
 pub mod fallback;
 pub mod r#impl;
@@ -53,6 +55,13 @@ impl From<IndexMap<String, Box<dyn ArtifactType>>> for ArtifactTypes {
     }
 }
 
+impl ArtifactTypes {
+    /// Returns the artifact type registered for the key, if any.
+    pub fn get(&self, key: &str) -> Option<&dyn ArtifactType> {
+        self.0.get(key).map(AsRef::as_ref)
+    }
+}
+
 impl Default for ArtifactTypes {
     fn default() -> Self {
         let mut m: IndexMap<String, Box<dyn ArtifactType>> = IndexMap::new();
@@ -121,16 +130,56 @@ pub trait ArtifactType: Send + Sync {
         setting: Option<&'a Value>,
         nightly_config: &'a NightlyConfig,
     ) -> Result<NightlyArtifactResponder, ArtifactError>;
+
+    /// Checks that the product setting for this artifact type has the shape the artifact type
+    /// expects. Returns a description of the problem otherwise.
+    fn validate_setting(&self, _setting: Option<&Value>) -> Result<(), String> {
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
 pub struct RenderableArtifact<'a> {
     pub icon_path: Option<Cow<'a, str>>,
     pub display_name: ArtifactDisplayTitle<'a>,
+    /// Tells the artifact apart from other artifacts of the same type.
+    pub label: Option<Cow<'a, str>>,
     pub modified_date: Option<Cow<'a, str>>,
     pub file_size: Option<Cow<'a, str>>,
+    /// Download URLs by endpoint key.
     pub urls: BTreeMap<Cow<'a, str>, Cow<'a, str>>,
+    /// File size and modification date on every endpoint that has the file, by endpoint key.
+    pub mirrors: BTreeMap<Cow<'a, str>, MirrorArtifactMetadata<'a>>,
     pub extra_info_markdown: Option<Cow<'a, str>>,
+    /// Checksums of the file by algorithm (`sha256`, `sha512`).
+    pub checksums: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+    pub signature: Option<ArtifactSignature<'a>>,
+}
+
+/// A detached signature of an artifact.
+#[derive(Debug)]
+pub struct ArtifactSignature<'a> {
+    /// The format of the signature (`sig`, `minisig` or `asc`).
+    pub format: Cow<'a, str>,
+    /// Download URLs of the signature by endpoint key.
+    pub urls: BTreeMap<Cow<'a, str>, Cow<'a, str>>,
+}
+
+#[derive(Debug)]
+pub struct MirrorArtifactMetadata<'a> {
+    pub modified_date: Option<Cow<'a, str>>,
+    pub file_size: Option<Cow<'a, str>>,
+}
+
+impl From<&StorageObject> for MirrorArtifactMetadata<'static> {
+    fn from(object: &StorageObject) -> Self {
+        Self {
+            modified_date: object
+                .last_modified
+                .map(|d| d.format("%Y-%m-%d %H:%M").to_string().into()),
+            file_size: filesizeformat(&object.size).ok().map(Into::into),
+        }
+    }
 }
 
 impl<'a> RenderableArtifact<'a> {
@@ -141,6 +190,22 @@ impl<'a> RenderableArtifact<'a> {
     pub fn display_subtitle(&'a self) -> &'a str {
         self.display_name.subtitle()
     }
+
+    /// File sizes on the endpoints that have the file, by endpoint key.
+    pub fn mirror_file_sizes(&self) -> BTreeMap<&str, &str> {
+        self.mirrors
+            .iter()
+            .filter_map(|(k, m)| m.file_size.as_deref().map(|v| (k.as_ref(), v)))
+            .collect()
+    }
+
+    /// Modification dates on the endpoints that have the file, by endpoint key.
+    pub fn mirror_modified_dates(&self) -> BTreeMap<&str, &str> {
+        self.mirrors
+            .iter()
+            .filter_map(|(k, m)| m.modified_date.as_deref().map(|v| (k.as_ref(), v)))
+            .collect()
+    }
 }
 
 pub async fn artifacts_describe<'a>(
@@ -158,78 +223,121 @@ pub async fn artifacts_describe<'a>(
 
 pub async fn artifacts_collect(
     product_name: &str,
-    settings: &HashMap<ArtifactKey, Value>,
+    product: &Product,
     version: &NamedVersion<'_>,
     ats: &ArtifactTypes,
-    endpoints: &Endpoints,
-    #[cfg(feature = "s3_bucket_list")] bucket_list: Option<Vec<s3::serde_types::ListBucketResult>>,
+    storage: &Storage,
+    mirror_listings: Option<MirrorListings>,
+    checksums: &HashMap<String, Checksums>,
 ) -> (
     Vec<RenderableArtifact<'static>>,
     Vec<RenderableArtifact<'static>>,
 ) {
     let mut supported = Vec::new();
     let mut unsupported = Vec::new();
-    for (key, download) in &version.info().downloads {
+    let downloads = expand_download_templates(
+        &version.info().downloads,
+        &product.download_templates,
+        product_name,
+        version.name(),
+    );
+    let downloads: Vec<_> = downloads
+        .iter()
+        .flat_map(|(key, downloads)| downloads.iter().map(move |download| (key, download)))
+        .collect();
+    for (key, download) in downloads {
         // TODO: Async could be improved here.
-        match get_artifact_info(
+        let artifact_info = match get_artifact_info(
             ats,
             key,
             product_name,
             version.name(),
             download,
-            settings.get(key),
+            product.settings.get(key),
         )
         .await
         {
-            Ok(artifact_info) => {
-                let mut modified_date = None;
-                let mut file_size: Option<u64> = None;
-                #[cfg(feature = "s3_bucket_list")]
-                if let Some(file_path) = artifact_info.file_path(product_name, version.name()) {
-                    if let Some((out_modified_date, out_file_size)) =
-                        get_file_metadata(&bucket_list, &file_path)
-                    {
-                        modified_date = Some(out_modified_date);
-                        file_size = Some(out_file_size);
-                    }
-                }
-                let target = if download.is_unsupported() {
-                    &mut unsupported
-                } else {
-                    &mut supported
-                };
-                target.push(RenderableArtifact {
-                    icon_path: artifact_info
-                        .icon()
-                        .map(ToString::to_string)
-                        .map(Into::into),
-                    display_name: artifact_info.display_name().clone_owned(),
-                    modified_date: modified_date
-                        .and_then(|d| chrono::DateTime::parse_from_rfc3339(d).ok())
-                        .map(|d| d.format("%Y-%m-%d %H:%M").to_string().into()),
-                    file_size: file_size
-                        .and_then(|file_size| filesizeformat(&file_size).ok())
-                        .map(Into::into),
-                    urls: artifact_info.urls(product_name, version.name(), endpoints),
-                    extra_info_markdown: artifact_info
-                        .extra_info_markdown
-                        .map(|s| s.to_string())
-                        .map(Into::into),
-                })
+            Ok(artifact_info) => artifact_info,
+            Err(err) => {
+                warn!(
+                    "Was unable to serve artifact '{}' for version '{}' of '{}': {}",
+                    key,
+                    version.name(),
+                    product_name,
+                    err
+                );
+                continue;
             }
-            Err(err) => warn!(
-                "Was unable to serve artifact '{}' for version '{}' of '{}': {}",
-                key,
-                version.name(),
-                product_name,
-                err
-            ),
+        };
+        let file_path = artifact_info.file_path(product_name, version.name());
+        let mut metadata = None;
+        let mut mirrors = BTreeMap::new();
+        if let (Some(file_path), Some(listings)) = (&file_path, mirror_listings.as_ref()) {
+            metadata = listings.get(file_path).map(MirrorArtifactMetadata::from);
+            mirrors = listings
+                .per_endpoint(file_path)
+                .map(|(endpoint, object)| (endpoint.to_string().into(), object.into()))
+                .collect();
         }
+        let metadata = metadata.unwrap_or(MirrorArtifactMetadata {
+            modified_date: None,
+            file_size: None,
+        });
+        let signature = match download.signature() {
+            Some((format, path)) => Some(ArtifactSignature {
+                format: format.into(),
+                urls: artifact_path(path)
+                    .urls(
+                        product_name,
+                        version.name(),
+                        storage,
+                        mirror_listings.as_ref(),
+                    )
+                    .await,
+            }),
+            None => None,
+        };
+        let file_checksums = download
+            .checksums()
+            .or(file_path.and_then(|file_path| checksums.get(&file_path)));
+        let target = if download.is_unsupported() {
+            &mut unsupported
+        } else {
+            &mut supported
+        };
+        target.push(RenderableArtifact {
+            icon_path: artifact_info
+                .icon()
+                .map(ToString::to_string)
+                .map(Into::into),
+            display_name: artifact_info.display_name().clone_owned(),
+            label: download.label().map(|label| label.to_string().into()),
+            modified_date: metadata.modified_date,
+            file_size: metadata.file_size,
+            urls: artifact_info
+                .urls(
+                    product_name,
+                    version.name(),
+                    storage,
+                    mirror_listings.as_ref(),
+                )
+                .await,
+            mirrors,
+            extra_info_markdown: artifact_info
+                .extra_info_markdown
+                .map(|s| s.to_string())
+                .map(Into::into),
+            checksums: file_checksums
+                .iter()
+                .map(|(algorithm, value)| (algorithm.into(), value.to_string().into()))
+                .collect(),
+            signature,
+        })
     }
     (supported, unsupported)
 }
 
-async fn get_artifact_info<'a>(
+pub(crate) async fn get_artifact_info<'a>(
     ats: &ArtifactTypes,
     key: &str,
     product_name: &'a str,
@@ -283,23 +391,6 @@ pub(crate) async fn get_artifact_nightly_download<'a>(
     }
 }
 
-#[cfg(feature = "s3_bucket_list")]
-fn get_file_metadata<'a>(
-    bucket_list: &'a Option<Vec<s3::serde_types::ListBucketResult>>,
-    file_path: &str,
-) -> Option<(&'a str, u64)> {
-    if let Some(bucket_list) = bucket_list {
-        for page in bucket_list {
-            for object in &page.contents {
-                if object.key == file_path {
-                    return Some((&object.last_modified, object.size));
-                }
-            }
-        }
-    }
-    None
-}
-
 #[derive(Error, Debug)]
 pub enum ArtifactError {
     #[error(
@@ -322,6 +413,63 @@ enum ArtifactPath<'a> {
     None,
 }
 
+impl<'a> ArtifactPath<'a> {
+    /// Returns the download URLs of the file by endpoint key. See `ArtifactInfo::urls`.
+    async fn urls(
+        &self,
+        product_name: &str,
+        version_name: &str,
+        storage: &Storage,
+        mirror_listings: Option<&MirrorListings>,
+    ) -> BTreeMap<Cow<'static, str>, Cow<'static, str>> {
+        match self {
+            ArtifactPath::File(file_name) => {
+                let file_path = format!("{}/{}/{}", product_name, version_name, file_name);
+                let mirror_listings =
+                    mirror_listings.filter(|listings| listings.get(&file_path).is_some());
+                let mut urls = BTreeMap::new();
+                for endpoint in storage.endpoints().get_all() {
+                    if mirror_listings.is_some_and(|listings| {
+                        listings.presence(&endpoint.key, &file_path) == ObjectPresence::Absent
+                    }) {
+                        continue;
+                    }
+                    let Some(backend) = storage.backend(&endpoint.key) else {
+                        continue;
+                    };
+                    match backend.download_url(&file_path).await {
+                        Ok(url) => {
+                            urls.insert(endpoint.key.clone().into(), url.into());
+                        }
+                        Err(err) => warn!(
+                            "Was unable to get the download URL of '{}' on {}: {}",
+                            file_path, endpoint.key, err
+                        ),
+                    }
+                }
+                urls
+            }
+            ArtifactPath::RemoteUrl(remote_url) => storage
+                .endpoints()
+                .get_all()
+                .iter()
+                .map(|e| (e.key.clone().into(), remote_url.to_string().into()))
+                .collect(),
+            ArtifactPath::None => BTreeMap::new(),
+        }
+    }
+}
+
+/// Returns the path of a file given in a download spec: A remote URL, or a path relative to the
+/// directory of the version.
+fn artifact_path(path: &str) -> ArtifactPath<'_> {
+    if path.contains("://") {
+        ArtifactPath::RemoteUrl(path.into())
+    } else {
+        ArtifactPath::File(path.into())
+    }
+}
+
 #[derive(Debug)]
 pub enum ArtifactDisplayTitle<'a> {
     Simple(Cow<'a, str>),
@@ -428,11 +576,11 @@ impl<'a> ArtifactInfo<'a> {
         self.extra_info_markdown = Some(value);
     }
 
-    pub fn display_name(&self) -> &ArtifactDisplayTitle {
+    pub fn display_name(&self) -> &ArtifactDisplayTitle<'_> {
         &self.display_name
     }
 
-    pub fn extra_info_markdown(&self) -> Option<&Cow<str>> {
+    pub fn extra_info_markdown(&self) -> Option<&Cow<'_, str>> {
         self.extra_info_markdown.as_ref()
     }
 
@@ -449,34 +597,17 @@ impl<'a> ArtifactInfo<'a> {
         }
     }
 
-    pub fn urls(
+    /// Returns the download URLs by endpoint key. If listings are given, endpoints that are known
+    /// not to have the file are left out, unless no listed endpoint has it.
+    pub async fn urls(
         &self,
         product_name: &str,
         version_name: &str,
-        endpoints: &Endpoints,
+        storage: &Storage,
+        mirror_listings: Option<&MirrorListings>,
     ) -> BTreeMap<Cow<'static, str>, Cow<'static, str>> {
-        match &self.path {
-            ArtifactPath::File(_) => endpoints
-                .get_all()
-                .iter()
-                .map(|e| {
-                    (
-                        e.display_name.clone().into(),
-                        format!(
-                            "{}/{}",
-                            &e.url,
-                            self.file_path(product_name, version_name).unwrap()
-                        )
-                        .into(),
-                    )
-                })
-                .collect(),
-            ArtifactPath::RemoteUrl(remote_url) => endpoints
-                .get_all()
-                .iter()
-                .map(|e| (e.display_name.clone().into(), remote_url.to_string().into()))
-                .collect(),
-            ArtifactPath::None => BTreeMap::new(),
-        }
+        self.path
+            .urls(product_name, version_name, storage, mirror_listings)
+            .await
     }
 }
@@ -0,0 +1,312 @@
+use indexmap::IndexMap;
+
+use crate::r#impl::pre_release::parse_pre_release;
+use crate::r#impl::release_map::{NamedVersion, ReleaseMap, VersionListEntry};
+use crate::r#impl::storage::{ChannelEntry, PreReleasePatternEntry, Product, VersionInfo};
+
+/// The channel of versions that are not pre-releases and are not in another channel.
+pub const STABLE: &str = "stable";
+const STABLE_DISPLAY_NAME: &str = "Stable";
+/// Display name of the pre-releases that are not in a channel.
+const NO_CHANNEL_DISPLAY_NAME: &str = "Other Pre-Releases";
+/// Display name of the versions of a product without channels besides stable.
+const ALL_DISPLAY_NAME: &str = "All Releases";
+/// Prefix of releases that refer to the latest version of a channel (`latest-beta`).
+pub const LATEST_PREFIX: &str = "latest-";
+
+/// The release channels of a product.
+pub struct Channels<'a> {
+    entries: Vec<&'a ChannelEntry>,
+    pre_release_patterns: &'a [PreReleasePatternEntry],
+}
+
+/// Listed versions of a channel, for the releases page.
+pub struct ChannelGroup<'a> {
+    /// None for the pre-releases that are not in a channel, or for all versions of a product
+    /// without channels besides stable.
+    pub key: Option<&'a str>,
+    pub display_name: &'a str,
+    pub versions: Vec<VersionListEntry<'a>>,
+}
+
+impl<'a> Channels<'a> {
+    /// The channels of the product, followed by the global channels it doesn't override.
+    pub fn new(
+        product: &'a Product,
+        global: &'a [ChannelEntry],
+        pre_release_patterns: &'a [PreReleasePatternEntry],
+    ) -> Self {
+        let mut entries: Vec<&ChannelEntry> = product.channels.iter().collect();
+        for entry in global {
+            if !entries.iter().any(|e| e.key == entry.key) {
+                entries.push(entry);
+            }
+        }
+        Self {
+            entries,
+            pre_release_patterns,
+        }
+    }
+
+    /// Returns the channel of a version: The channel it sets, otherwise the first channel whose
+    /// pattern matches its name, otherwise `stable` if it is not a pre-release. Pre-releases
+    /// that don't match a channel are in no channel.
+    pub fn of(&self, name: &str, info: &'a VersionInfo) -> Option<&'a str> {
+        if let Some(channel) = &info.channel {
+            return Some(channel);
+        }
+        self.entries
+            .iter()
+            .copied()
+            .find(|entry| {
+                entry
+                    .pattern
+                    .as_ref()
+                    .is_some_and(|pattern| pattern.is_match(name))
+            })
+            .map(|entry| entry.key.as_str())
+            .or_else(|| {
+                parse_pre_release(name, self.pre_release_patterns)
+                    .is_none()
+                    .then_some(STABLE)
+            })
+    }
+
+    /// The display name of a channel. Channels that are only set by versions are shown by
+    /// their key.
+    pub fn display_name(&self, key: &'a str) -> &'a str {
+        match self.entries.iter().copied().find(|entry| entry.key == key) {
+            Some(entry) => &entry.display_name,
+            None if key == STABLE => STABLE_DISPLAY_NAME,
+            None => key,
+        }
+    }
+
+    /// Returns the latest listed version of a channel. Unlike `ReleaseMap::latest`, this can
+    /// be a pre-release.
+    pub fn latest(&self, versions: &'a ReleaseMap, channel: &str) -> Option<NamedVersion<'a>> {
+        versions
+            .map()
+            .iter()
+            .rev()
+            .find(|(name, info)| info.is_listed() && self.of(name, info) == Some(channel))
+            .map(Into::into)
+    }
+
+    /// Groups the listed versions by channel, newest first in each channel. The stable channel
+    /// comes first, then the configured channels, the channels only set by versions and the
+    /// pre-releases that are not in a channel. Channels without versions are left out.
+    ///
+    /// If `only` is set, only the group of that channel is returned. Otherwise, if there are no
+    /// versions in channels besides stable, all versions are returned in one group.
+    pub fn group(&self, versions: &'a ReleaseMap, only: Option<&str>) -> Vec<ChannelGroup<'a>> {
+        let mut groups: IndexMap<Option<&str>, Vec<VersionListEntry>> = IndexMap::new();
+        groups.insert(Some(STABLE), Vec::new());
+        for entry in self.entries.iter().copied() {
+            groups.insert(Some(&entry.key), Vec::new());
+        }
+        for version in versions.list(self.pre_release_patterns) {
+            let info = &versions.map()[version.name];
+            groups
+                .entry(self.of(version.name, info))
+                .or_default()
+                .push(version);
+        }
+        if let Some(only) = only {
+            groups.retain(|key, _| *key == Some(only));
+        } else if groups
+            .iter()
+            .all(|(key, versions)| versions.is_empty() || key.is_none_or(|key| key == STABLE))
+        {
+            return vec![ChannelGroup {
+                key: None,
+                display_name: ALL_DISPLAY_NAME,
+                versions: versions
+                    .list(self.pre_release_patterns)
+                    .into_iter()
+                    .collect(),
+            }];
+        }
+        if let Some(no_channel) = groups.shift_remove(&None) {
+            groups.insert(None, no_channel);
+        }
+        groups
+            .into_iter()
+            .filter(|(_, versions)| !versions.is_empty())
+            .map(|(key, versions)| ChannelGroup {
+                key,
+                display_name: key.map_or(NO_CHANNEL_DISPLAY_NAME, |key| self.display_name(key)),
+                versions,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRODUCT: &str = r#"
+name: Tool
+channels:
+  - key: beta
+    display_name: Tool Beta
+    pattern: "-beta"
+versions:
+  1.0.0: { date: 2023-01-02 }
+  1.1.0-beta.1: { date: 2023-01-02 }
+  1.1.0-rc.1: { date: 2023-01-02 }
+  1.1.0: { date: 2023-01-02 }
+  1.2.0-beta.1: { date: 2023-01-02 }
+  1.2.0-beta.2: { date: 2023-01-02 }
+  1.2.0-nightly.1: { date: 2023-01-02 }
+  2.0.0-rc.1:
+    date: 2023-01-02
+    channel: lts
+  2.0.0-alpha.1: { date: 2023-01-02 }
+"#;
+    const GLOBAL: &str = r#"
+- key: beta
+  display_name: Beta
+  pattern: "-(beta|rc)"
+- key: rc
+  display_name: Release Candidate
+  pattern: "-rc"
+"#;
+    const PRE_RELEASE_PATTERNS: &str = r#"
+- pattern: "-(alpha|beta|rc|nightly)"
+  display_name: Pre-Release
+"#;
+
+    struct Fixture {
+        product: Product,
+        global: Vec<ChannelEntry>,
+        pre_release_patterns: Vec<PreReleasePatternEntry>,
+    }
+
+    impl Fixture {
+        fn new(product: &str) -> Self {
+            Self {
+                product: serde_yaml::from_str(product).unwrap(),
+                global: serde_yaml::from_str(GLOBAL).unwrap(),
+                pre_release_patterns: serde_yaml::from_str(PRE_RELEASE_PATTERNS).unwrap(),
+            }
+        }
+
+        fn channels(&self) -> Channels<'_> {
+            Channels::new(&self.product, &self.global, &self.pre_release_patterns)
+        }
+
+        fn of(&self, name: &str) -> Option<&str> {
+            let info = &self.product.versions.map()[name];
+            self.channels().of(name, info)
+        }
+
+        fn groups(&self, only: Option<&str>) -> Vec<(Option<&str>, &str, Vec<&str>)> {
+            self.channels()
+                .group(&self.product.versions, only)
+                .into_iter()
+                .map(|group| {
+                    let versions = group.versions.iter().map(|v| v.name).collect();
+                    (group.key, group.display_name, versions)
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn product_channels_override_global_channels() {
+        let fixture = Fixture::new(PRODUCT);
+        let channels = fixture.channels();
+        assert_eq!(channels.display_name("beta"), "Tool Beta");
+        assert_eq!(channels.display_name("rc"), "Release Candidate");
+        // The product's beta pattern doesn't match release candidates, the global one would.
+        assert_eq!(fixture.of("1.1.0-beta.1"), Some("beta"));
+        assert_eq!(fixture.of("1.1.0-rc.1"), Some("rc"));
+    }
+
+    #[test]
+    fn versions_without_channel() {
+        let fixture = Fixture::new(PRODUCT);
+        assert_eq!(fixture.of("1.0.0"), Some(STABLE));
+        assert_eq!(fixture.of("1.2.0-nightly.1"), None);
+        assert_eq!(fixture.of("2.0.0-alpha.1"), None);
+        // Channels set by versions take precedence over patterns and need no entry.
+        assert_eq!(fixture.of("2.0.0-rc.1"), Some("lts"));
+        assert_eq!(fixture.channels().display_name("lts"), "lts");
+        assert_eq!(fixture.channels().display_name(STABLE), "Stable");
+    }
+
+    #[test]
+    fn groups_in_channel_order_newest_first() {
+        let fixture = Fixture::new(PRODUCT);
+        assert_eq!(
+            fixture.groups(None),
+            [
+                (Some(STABLE), "Stable", vec!["1.1.0", "1.0.0"]),
+                (
+                    Some("beta"),
+                    "Tool Beta",
+                    vec!["1.2.0-beta.2", "1.2.0-beta.1", "1.1.0-beta.1"]
+                ),
+                (Some("rc"), "Release Candidate", vec!["1.1.0-rc.1"]),
+                (Some("lts"), "lts", vec!["2.0.0-rc.1"]),
+                (
+                    None,
+                    NO_CHANNEL_DISPLAY_NAME,
+                    vec!["2.0.0-alpha.1", "1.2.0-nightly.1"]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn group_of_one_channel() {
+        let fixture = Fixture::new(PRODUCT);
+        assert_eq!(
+            fixture.groups(Some("beta")),
+            [(
+                Some("beta"),
+                "Tool Beta",
+                vec!["1.2.0-beta.2", "1.2.0-beta.1", "1.1.0-beta.1"]
+            )]
+        );
+        assert_eq!(fixture.groups(Some("unknown")), []);
+    }
+
+    #[test]
+    fn single_group_without_channels() {
+        let fixture = Fixture::new(
+            r#"
+name: Tool
+versions:
+  1.0.0: { date: 2023-01-02 }
+  1.1.0-alpha.1: { date: 2023-01-02 }
+  1.1.0: { date: 2023-01-02 }
+"#,
+        );
+        assert_eq!(
+            fixture.groups(None),
+            [(
+                None,
+                ALL_DISPLAY_NAME,
+                vec!["1.1.0", "1.1.0-alpha.1", "1.0.0"]
+            )]
+        );
+    }
+
+    #[test]
+    fn latest_of_channel() {
+        let fixture = Fixture::new(PRODUCT);
+        let channels = fixture.channels();
+        let latest = |channel| {
+            channels
+                .latest(&fixture.product.versions, channel)
+                .map(|version| version.name().to_string())
+        };
+        assert_eq!(latest(STABLE).as_deref(), Some("1.1.0"));
+        assert_eq!(latest("beta").as_deref(), Some("1.2.0-beta.2"));
+        assert_eq!(latest("lts").as_deref(), Some("2.0.0-rc.1"));
+        assert_eq!(latest("unknown"), None);
+    }
+}
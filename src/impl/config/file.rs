@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use indexmap::IndexMap;
+use log::info;
+use serde::Deserialize;
+
+use crate::r#impl::config::{
+    endpoint_key, AdminToken, BannerEnable, ConfigError, ConfigVars, GithubToken,
+    HealthCheckInterval, HomeUrl, MaxmindDbPath, RawEndpoint, ReleaseInfoDomain, ReleaseInfoEnable,
+    SelfName, SimpleConfig, SimpleConfigBool, Theme,
+};
+#[cfg(feature = "amazon_translate")]
+use crate::r#impl::translate::{TranslateAwsKeyId, TranslateAwsSecretAccessKey};
+
+/// Path to the optional config file.
+struct ConfigFilePath {}
+
+impl SimpleConfig for ConfigFilePath {
+    const VAR_NAME: &'static str = "DEPBOX_CONFIG_FILE";
+}
+
+/// The config file. It is parsed as TOML if its name ends with `.toml` and as YAML otherwise.
+/// Every value can be overwritten with the corresponding environment variable.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct ConfigFile {
+    home_url: Option<String>,
+    self_name: Option<String>,
+    theme: Option<String>,
+    release_info_enable: Option<bool>,
+    release_info_domain: Option<String>,
+    banner_enable: Option<bool>,
+    maxminddb_path: Option<String>,
+    health_check_interval: Option<u64>,
+    admin_token: Option<String>,
+    github_token: Option<String>,
+    translate: Option<TranslateSection>,
+    /// Endpoints by key, in order of preference.
+    #[serde(default)]
+    endpoints: IndexMap<String, EndpointDefinition>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "amazon_translate"), allow(dead_code))]
+struct TranslateSection {
+    aws_access_key_id: Option<String>,
+    aws_secret_access_key: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EndpointDefinition {
+    display_name: Option<String>,
+    url: Option<String>,
+    location: Option<LocationDefinition>,
+    path: Option<PathBuf>,
+    bucket: Option<String>,
+    region: Option<String>,
+    list_endpoint: Option<String>,
+    path_style: Option<bool>,
+    access_key: Option<String>,
+    secret_key: Option<String>,
+    presign_expiry: Option<u32>,
+}
+
+/// A location, either as `[latitude, longitude]` or as `"latitude longitude"`, like in the
+/// environment variables.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum LocationDefinition {
+    Pair([f64; 2]),
+    String(String),
+}
+
+impl ConfigFile {
+    /// Loads the config file, if one is configured.
+    pub(super) fn load() -> Result<Option<Self>, ConfigError> {
+        let Ok(path) = ConfigFilePath::get_checked(&ConfigVars::default()) else {
+            return Ok(None);
+        };
+        info!("Config file: {}", path);
+        let content = fs::read_to_string(&path).map_err(|source| ConfigError::FileRead {
+            path: path.clone(),
+            source,
+        })?;
+        let parsed = if path.ends_with(".toml") {
+            toml::from_str(&content).map_err(|e| e.to_string())
+        } else {
+            serde_yaml::from_str(&content).map_err(|e| e.to_string())
+        };
+        parsed
+            .map(Some)
+            .map_err(|message| ConfigError::FileParse { path, message })
+    }
+
+    /// Returns the values of the file by the names of their environment variables, which take
+    /// precedence, and the endpoints of the file, which are merged with the endpoints from the
+    /// environment separately.
+    pub(super) fn into_values(self) -> (ConfigVars, IndexMap<String, RawEndpoint>) {
+        let mut values = HashMap::new();
+        let mut set_value = |var_name: &'static str, value: Option<String>| {
+            if let Some(value) = value {
+                values.insert(var_name, value);
+            }
+        };
+        fn bool_value(value: bool) -> &'static str {
+            if value {
+                "1"
+            } else {
+                "0"
+            }
+        }
+
+        set_value(HomeUrl::VAR_NAME, self.home_url);
+        set_value(SelfName::VAR_NAME, self.self_name);
+        set_value(Theme::VAR_NAME, self.theme);
+        set_value(
+            ReleaseInfoEnable::VAR_NAME,
+            self.release_info_enable.map(|v| bool_value(v).to_string()),
+        );
+        set_value(ReleaseInfoDomain::VAR_NAME, self.release_info_domain);
+        set_value(
+            BannerEnable::VAR_NAME,
+            self.banner_enable.map(|v| bool_value(v).to_string()),
+        );
+        set_value(MaxmindDbPath::VAR_NAME, self.maxminddb_path);
+        set_value(
+            HealthCheckInterval::VAR_NAME,
+            self.health_check_interval.map(|v| v.to_string()),
+        );
+        set_value(AdminToken::VAR_NAME, self.admin_token);
+        set_value(GithubToken::VAR_NAME, self.github_token);
+        if let Some(translate) = self.translate {
+            #[cfg(feature = "amazon_translate")]
+            {
+                set_value(TranslateAwsKeyId::VAR_NAME, translate.aws_access_key_id);
+                set_value(
+                    TranslateAwsSecretAccessKey::VAR_NAME,
+                    translate.aws_secret_access_key,
+                );
+            }
+            #[cfg(not(feature = "amazon_translate"))]
+            {
+                let _ = translate;
+                log::warn!("Translate options in the config file are ignored, translation is not supported by this build.");
+            }
+        }
+
+        let endpoints = self
+            .endpoints
+            .into_iter()
+            .map(|(key, e)| {
+                (
+                    endpoint_key(&key),
+                    RawEndpoint {
+                        display_name: e.display_name,
+                        url: e.url,
+                        location: e.location.map(|location| match location {
+                            LocationDefinition::Pair([lat, lon]) => format!("{} {}", lat, lon),
+                            LocationDefinition::String(s) => s,
+                        }),
+                        local_path: e.path,
+                        bucket: e.bucket,
+                        region: e.region,
+                        list_endpoint: e.list_endpoint,
+                        path_style: e.path_style.map(|v| bool_value(v).to_string()),
+                        access_key: e.access_key,
+                        secret_key: e.secret_key,
+                        presign_expiry: e.presign_expiry.map(|v| v.to_string()),
+                        order: None,
+                    },
+                )
+            })
+            .collect();
+        (ConfigVars { file: values }, endpoints)
+    }
+}
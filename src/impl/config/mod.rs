@@ -0,0 +1,1378 @@
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::io;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use dotenv::dotenv;
+#[cfg(feature = "geoip")]
+use geoutils::Location;
+use indexmap::IndexMap;
+use itertools::Itertools;
+use lazy_static::lazy_static;
+use log::{debug, info, warn};
+use regex::Regex;
+use thiserror::Error;
+
+use crate::r#impl::artifacttype::ArtifactTypes;
+use crate::r#impl::config::file::ConfigFile;
+#[cfg(feature = "geoip")]
+use crate::r#impl::geoip::{find_best_location, self_server_ip, sort_by_location};
+#[cfg(feature = "github")]
+use crate::r#impl::github::GithubClient;
+use crate::r#impl::storage::{Checksums, MirrorListings, ProductsConfig, Storage, StorageError};
+#[cfg(feature = "amazon_translate")]
+use crate::r#impl::translate::TranslateConfig;
+
+#[cfg(not(feature = "geoip"))]
+/// If the GeoIP feature is not enabled, Location is simply a unit type.
+type Location = ();
+
+mod file;
+
+/// Error while loading the configuration.
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to load environment: {0}.")]
+    Env(#[from] dotenv::Error),
+    #[error("Failed to read config file {path}: {source}")]
+    FileRead { path: String, source: io::Error },
+    #[error("Invalid config file {path}: {message}")]
+    FileParse { path: String, message: String },
+    #[error("Invalid configuration:\n{}", .0.iter().map(|e| format!("  - {}", e)).join("\n"))]
+    Invalid(Vec<String>),
+}
+
+lazy_static! {
+    static ref ENDPOINTS_PATTERN: Regex = Regex::new(
+        r"^DEPBOX_S3_ENDPOINT__(\d+)__(.+)__(DISPLAY_NAME|URL|LOC|PATH|BUCKET|REGION|LIST_ENDPOINT|PATH_STYLE|ACCESS_KEY|SECRET_KEY|PRESIGN_EXPIRY)$"
+    )
+    .unwrap();
+}
+
+pub struct Config {
+    #[cfg(feature = "geoip")]
+    geoipdb: Option<maxminddb::Reader<Vec<u8>>>,
+    banner: bool,
+    health_check_interval: Option<Duration>,
+    admin_token: Option<String>,
+    release_info: Option<String>,
+    storage: Storage,
+    artifacttypes: ArtifactTypes,
+    theme: String,
+    home_url: String,
+    self_name: String,
+    #[cfg(feature = "amazon_translate")]
+    translate: Option<TranslateConfig>,
+}
+
+impl Config {
+    /// Loads the configuration from the environment, the .env file and the config file.
+    /// All problems with the configuration are collected and returned together.
+    pub fn load(artifacttypes: ArtifactTypes) -> Result<Self, ConfigError> {
+        debug!("-- Loading config from environment, .env file and config file... --");
+
+        Self::init_env()?;
+        let (vars, file_endpoints) = ConfigFile::load()?
+            .map(ConfigFile::into_values)
+            .unwrap_or_default();
+
+        let mut errors = Vec::new();
+
+        #[cfg(feature = "geoip")]
+        let geoipdb = match Self::load_geoipdb(&vars) {
+            Some(Ok(geoipdb)) => Some(geoipdb),
+            Some(Err(err)) => {
+                errors.push(format!(
+                    "Failed to load Maxmind-compatible GeoIP database: {}",
+                    err
+                ));
+                None
+            }
+            None => None,
+        };
+
+        let endpoints = Endpoints::load(
+            file_endpoints,
+            #[cfg(feature = "geoip")]
+            geoipdb.as_ref(),
+        );
+        let endpoints = match endpoints {
+            Ok(endpoints) => Some(endpoints),
+            Err(endpoint_errors) => {
+                errors.extend(endpoint_errors);
+                None
+            }
+        };
+
+        let health_check_interval = HealthCheckInterval::get_duration(&vars)
+            .map_err(|err| errors.push(err))
+            .unwrap_or_default();
+        errors.extend(Self::check_env(&vars, endpoints.as_ref()));
+
+        let storage = endpoints.and_then(|endpoints| {
+            Storage::new(endpoints)
+                .map_err(|err| errors.push(format!("Failed to initialize storage: {}", err)))
+                .ok()
+        });
+        let Some(storage) = storage.filter(|_| errors.is_empty()) else {
+            return Err(ConfigError::Invalid(errors));
+        };
+
+        #[cfg(feature = "github")]
+        GithubClient::init_token(GithubToken::get(&vars));
+
+        Ok(Self {
+            storage,
+            #[cfg(feature = "geoip")]
+            geoipdb,
+            banner: BannerEnable::get(&vars),
+            health_check_interval,
+            admin_token: AdminToken::get_checked(&vars)
+                .ok()
+                .filter(|token| !token.is_empty()),
+            release_info: match ReleaseInfoEnable::get(&vars) {
+                true => Some(ReleaseInfoDomain::get(&vars)),
+                false => None,
+            },
+            artifacttypes,
+            theme: Theme::get(&vars),
+            home_url: HomeUrl::get(&vars),
+            self_name: SelfName::get_checked(&vars)
+                .ok()
+                .unwrap_or_else(SelfName::default_value),
+            #[cfg(feature = "amazon_translate")]
+            translate: TranslateConfig::get(&vars),
+        })
+    }
+
+    pub fn provide_banner(&self) -> bool {
+        self.banner
+    }
+
+    pub fn get_release_info(&self) -> &Option<String> {
+        &self.release_info
+    }
+
+    /// Returns the token required for the admin routes, or None if they are disabled.
+    pub fn admin_token(&self) -> Option<&str> {
+        self.admin_token.as_deref()
+    }
+
+    fn init_env() -> Result<(), dotenv::Error> {
+        dotenv()
+            .map(|_| ())
+            .or_else(|err| if err.not_found() { Ok(()) } else { Err(err) })
+    }
+
+    /// Logs the configuration and returns the problems with it.
+    fn check_env(vars: &ConfigVars, endpoints: Option<&Endpoints>) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        if let Some(endpoints) = endpoints {
+            info!("Endpoints:");
+            for endpoint in endpoints.get_all() {
+                info!("-> {:?}", endpoint);
+            }
+        }
+
+        match SelfName::get_checked(vars) {
+            Ok(value) => info!("Self Name (title shown in browser): {}", value),
+            Err(_) => warn!(
+                "Self Name (title shown in browser) not configured. Using default: {}",
+                SelfName::default_value()
+            ),
+        }
+
+        match HomeUrl::get_checked(vars) {
+            Ok(value) => info!("Home URL: {}", value),
+            Err(err) => errors.push(format!("Home URL not configured: {}", err)),
+        }
+
+        match Theme::get_checked(vars) {
+            Ok(value) => {
+                info!("Theme: {}", value);
+                if !PathBuf::from(format!("view/static/theme/{}", value)).exists() {
+                    errors.push(format!(
+                        "Theme files directory (view/static/theme/{}) does not exist.",
+                        value
+                    ));
+                }
+            }
+            Err(err) => errors.push(format!("Theme not configured: {}", err)),
+        }
+
+        if ReleaseInfoEnable::get(vars) {
+            info!("Release Info: enabled");
+            match ReleaseInfoDomain::get_checked(vars) {
+                Ok(value) => info!("Release Info Domain: {}", value),
+                Err(err) => errors.push(format!("Release Info Domain not configured: {}", err)),
+            }
+        } else {
+            info!("Release Info: disabled");
+        }
+
+        if BannerEnable::get(vars) {
+            info!("Serving banner: enabled (/banner for URL, /banner.png for image)");
+        } else {
+            info!("Serving banner: disabled");
+        }
+
+        match AdminToken::get_checked(vars) {
+            Ok(token) if !token.is_empty() => info!("Admin routes: enabled"),
+            _ => info!("Admin routes: disabled (no admin token configured)"),
+        }
+
+        match HealthCheckInterval::get_duration(vars) {
+            Ok(Some(interval)) => info!("Endpoint health check interval: {:?}", interval),
+            Ok(None) => info!("Endpoint health checks: disabled"),
+            // Reported by the caller.
+            Err(_) => {}
+        }
+
+        #[cfg(feature = "github")]
+        if let Err(err) = GithubToken::get_checked(vars) {
+            errors.push(format!("GitHub token not configured: {}", err));
+        }
+
+        match MaxmindDbPath::get_checked(vars) {
+            Ok(value) => info!("Maxmind DB path: {}", value),
+            Err(err) => {
+                warn!("Maxmind DB path not configured: {}", err);
+                warn!("-> GeoIP mirror selection disabled.");
+            }
+        }
+
+        errors
+    }
+
+    #[cfg(feature = "geoip")]
+    fn load_geoipdb(
+        vars: &ConfigVars,
+    ) -> Option<Result<maxminddb::Reader<Vec<u8>>, maxminddb::MaxMindDBError>> {
+        MaxmindDbPath::get_checked(vars)
+            .map(maxminddb::Reader::open_readfile)
+            .ok()
+    }
+
+    pub fn self_name(&self) -> &str {
+        self.self_name.as_str()
+    }
+
+    /// Returns the URL of the first available endpoint, used for files that are linked
+    /// directly, such as icons. Public endpoints are preferred.
+    pub fn default_endpoint_url(&self) -> &str {
+        let endpoints = self.storage.available_endpoints();
+        &endpoints
+            .iter()
+            .find(|e| !e.is_private())
+            .unwrap_or(&endpoints[0])
+            .url
+    }
+
+    pub fn theme(&self) -> &str {
+        self.theme.as_str()
+    }
+
+    pub fn home_url(&self) -> &str {
+        self.home_url.as_str()
+    }
+
+    pub fn artifact_types(&self) -> &ArtifactTypes {
+        &self.artifacttypes
+    }
+
+    pub fn endpoints(&self) -> &Endpoints {
+        self.storage.endpoints()
+    }
+
+    /// Starts the background tasks of Deposit Box, such as the endpoint health checker.
+    /// Must be called from within a Tokio runtime, e.g. on liftoff of Rocket.
+    pub fn spawn_background_tasks(&self) {
+        if let Some(interval) = self.health_check_interval {
+            self.storage.spawn_health_checker(interval);
+        }
+    }
+
+    pub fn storage(&self) -> &Storage {
+        &self.storage
+    }
+
+    /// Returns the product configuration, or an error on error. The result may be cached.
+    pub async fn get_config(&self) -> Result<ProductsConfig, StorageError> {
+        self.storage.get_config().await
+    }
+
+    /// Returns the banner target URL, may be cached.
+    pub async fn get_banner_url_url(&self) -> Option<String> {
+        self.get_config()
+            .await
+            .ok()
+            .and_then(|p| p.banner.map(|b| b.url_file))
+    }
+
+    /// Returns the banner PNG URL, may be cached.
+    pub async fn get_banner_png_url(&self) -> Option<String> {
+        self.get_config()
+            .await
+            .ok()
+            .and_then(|p| p.banner.map(|b| b.image_file))
+    }
+
+    /// Returns the endpoint closest to the given address. Endpoints that are down are excluded.
+    #[cfg(feature = "geoip")]
+    pub fn find_best_location(&self, addr: IpAddr) -> &Endpoint {
+        let endpoints = self.storage.available_endpoints();
+        match &self.geoipdb {
+            None => endpoints[0],
+            Some(geoipdb) => find_best_location(&endpoints, geoipdb, addr),
+        }
+    }
+
+    /// Returns the first endpoint. Endpoints that are down are excluded.
+    #[cfg(not(feature = "geoip"))]
+    pub fn find_best_location(&self, _addr: IpAddr) -> &Endpoint {
+        self.storage.available_endpoints()[0]
+    }
+
+    /// Returns the listings of all objects with keys starting with `prefix` (usually
+    /// `product/version/`) of every endpoint, or None, if no endpoint provides a listing.
+    /// The result may be cached. If no listing can be retrieved a warning will be logged on the
+    /// first call to this function.
+    pub async fn get_mirror_listings(&self, prefix: &str) -> Option<MirrorListings> {
+        self.storage.get_mirror_listings(prefix).await
+    }
+
+    /// Returns the checksums from the checksum files of a version, by path of the file.
+    /// The result may be cached.
+    pub async fn get_checksums(
+        &self,
+        product_key: &str,
+        version_name: &str,
+        checksum_files: &[String],
+    ) -> HashMap<String, Checksums> {
+        self.storage
+            .get_checksums(product_key, version_name, checksum_files)
+            .await
+    }
+
+    #[cfg(feature = "amazon_translate")]
+    /// Returns an Amazon Translate client, if available.
+    pub fn get_translate_client(&self) -> Option<&aws_sdk_translate::Client> {
+        self.translate.as_ref().map(|v| &v.client)
+    }
+}
+
+/// The configuration values: the environment variables, with the values of the config file as
+/// fallback. The values of the file are not written to the environment, which must not be
+/// modified while other threads may read it.
+#[derive(Debug, Default)]
+pub(crate) struct ConfigVars {
+    file: HashMap<&'static str, String>,
+}
+
+impl ConfigVars {
+    pub(crate) fn var(&self, var_name: &str) -> Result<String, env::VarError> {
+        match env::var(var_name) {
+            Err(env::VarError::NotPresent) => self
+                .file
+                .get(var_name)
+                .cloned()
+                .ok_or(env::VarError::NotPresent),
+            result => result,
+        }
+    }
+}
+
+pub(crate) trait SimpleConfig {
+    const VAR_NAME: &'static str;
+
+    fn get(vars: &ConfigVars) -> String {
+        Self::get_checked(vars).expect("Expected getting a config variable value.")
+    }
+
+    fn get_checked(vars: &ConfigVars) -> Result<String, env::VarError> {
+        vars.var(Self::VAR_NAME)
+    }
+}
+
+trait SimpleConfigBool {
+    const VAR_NAME: &'static str;
+
+    fn get(vars: &ConfigVars) -> bool {
+        vars.var(Self::VAR_NAME).is_ok_and(|x| x.trim() != "0")
+    }
+}
+
+/// Prefix of the route under which the files of local endpoints without a URL are served.
+pub const LOCAL_FILES_ROUTE: &str = "/_files";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Endpoint {
+    pub key: String,
+    pub display_name: String,
+    pub url: String,
+    pub location: Location,
+    /// If set, the files of this endpoint are read from this local directory instead of
+    /// being requested from `url`.
+    pub local_path: Option<PathBuf>,
+    /// How to list the bucket of this endpoint. None for endpoints without a bucket listing.
+    pub s3: Option<S3Addressing>,
+}
+
+impl Endpoint {
+    /// Whether the endpoint is a private bucket that can only be accessed with credentials.
+    pub fn is_private(&self) -> bool {
+        self.s3.as_ref().is_some_and(|s3| s3.credentials.is_some())
+    }
+}
+
+pub struct Endpoints {
+    _loaded: Vec<Endpoint>,
+    _loaded_map: HashMap<String, Endpoint>,
+}
+
+impl Endpoints {
+    /// Loads the endpoints from the config file and the environment. Values from the environment
+    /// take precedence. Returns a description of every invalid or incomplete endpoint on error.
+    pub(crate) fn load(
+        file_endpoints: IndexMap<String, RawEndpoint>,
+        #[cfg(feature = "geoip")] geoipdb: Option<&maxminddb::Reader<Vec<u8>>>,
+    ) -> Result<Self, Vec<String>> {
+        #[allow(unused_mut)]
+        let mut endpoints = Self::do_load(file_endpoints, env::vars())?;
+
+        #[cfg(feature = "geoip")]
+        if let Some(geoipdb) = geoipdb {
+            sort_by_location(&mut endpoints, geoipdb, self_server_ip());
+        }
+
+        Ok(Self {
+            _loaded_map: endpoints
+                .iter()
+                .map(|v| (v.key.clone(), v.clone()))
+                .collect(),
+            _loaded: endpoints,
+        })
+    }
+
+    pub fn get_all(&self) -> &[Endpoint] {
+        self._loaded.as_slice()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Endpoint> {
+        self._loaded_map.get(key)
+    }
+
+    fn do_load(
+        mut endpoints: IndexMap<String, RawEndpoint>,
+        env_vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Vec<Endpoint>, Vec<String>> {
+        for (key, value) in env_vars {
+            let Some(captures) = ENDPOINTS_PATTERN.captures(&key) else {
+                continue;
+            };
+            let entry = endpoints
+                .entry(endpoint_key(&captures[2]))
+                .or_insert_with(|| RawEndpoint {
+                    order: captures[1].parse().ok(),
+                    ..Default::default()
+                });
+            match &captures[3] {
+                "DISPLAY_NAME" => entry.display_name = Some(value),
+                "URL" => entry.url = Some(value),
+                "LOC" => entry.location = Some(value),
+                "PATH" => entry.local_path = Some(PathBuf::from(value)),
+                "BUCKET" => entry.bucket = Some(value),
+                "REGION" => entry.region = Some(value),
+                "LIST_ENDPOINT" => entry.list_endpoint = Some(value),
+                "PATH_STYLE" => entry.path_style = Some(value),
+                "ACCESS_KEY" => entry.access_key = Some(value),
+                "SECRET_KEY" => entry.secret_key = Some(value),
+                "PRESIGN_EXPIRY" => entry.presign_expiry = Some(value),
+                _ => unreachable!(),
+            }
+        }
+
+        if endpoints.is_empty() {
+            return Err(vec!["No endpoints configured.".to_string()]);
+        }
+
+        let mut final_endpoints = Vec::with_capacity(endpoints.len());
+        let mut errors = Vec::new();
+        // Endpoints from the config file come first, in the order of the file.
+        let endpoints_iter = endpoints
+            .into_iter()
+            .sorted_by_key(|(_, e)| (e.order.is_some(), e.order));
+        for (key, e) in endpoints_iter {
+            match e.resolve(&key) {
+                Ok(endpoint) => final_endpoints.push(endpoint),
+                Err(problems) => errors.push(format!("Endpoint {}: {}", key, problems.join(" "))),
+            }
+        }
+        if errors.is_empty() {
+            Ok(final_endpoints)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// The unvalidated values of a single endpoint, from the config file or the
+/// `DEPBOX_S3_ENDPOINT__<ORDER>__<KEY>__<FIELD>` environment variables.
+#[derive(Debug, Default)]
+pub(crate) struct RawEndpoint {
+    display_name: Option<String>,
+    url: Option<String>,
+    location: Option<String>,
+    local_path: Option<PathBuf>,
+    bucket: Option<String>,
+    region: Option<String>,
+    list_endpoint: Option<String>,
+    path_style: Option<String>,
+    access_key: Option<String>,
+    secret_key: Option<String>,
+    presign_expiry: Option<String>,
+    /// The order from the environment variable names, None for endpoints from the config file.
+    order: Option<usize>,
+}
+
+impl RawEndpoint {
+    /// Validates the values. Returns every problem found on error.
+    fn resolve(self, key: &str) -> Result<Endpoint, Vec<String>> {
+        let mut problems = Vec::new();
+        if self.display_name.is_none() {
+            problems.push("DISPLAY_NAME is missing.".to_string());
+        }
+        // Local endpoints without a URL are served by Deposit Box itself.
+        let url = self.url.or_else(|| {
+            self.local_path
+                .as_ref()
+                .map(|_| format!("{}/{}", LOCAL_FILES_ROUTE, key))
+        });
+        if url.is_none() {
+            problems.push("URL (or PATH for local endpoints) is missing.".to_string());
+        }
+        let location = match self.location.as_deref().map(parse_loc) {
+            Some(Ok(location)) => Some(location),
+            Some(Err(err)) => {
+                problems.push(err);
+                None
+            }
+            None => {
+                problems.push("LOC is missing.".to_string());
+                None
+            }
+        };
+        let s3 = match (&url, &self.local_path) {
+            (Some(url), None) => {
+                S3Credentials::new(self.access_key, self.secret_key, self.presign_expiry)
+                    .and_then(|credentials| {
+                        S3Addressing::resolve(
+                            url,
+                            self.bucket,
+                            self.region,
+                            self.list_endpoint,
+                            self.path_style.map(|v| v.trim() != "0"),
+                            credentials,
+                        )
+                    })
+                    .map_err(|err| problems.push(err))
+                    .unwrap_or_default()
+            }
+            _ => None,
+        };
+        match (self.display_name, url, location) {
+            (Some(display_name), Some(url), Some(location)) if problems.is_empty() => {
+                Ok(Endpoint {
+                    key: key.to_string(),
+                    display_name,
+                    url,
+                    location,
+                    local_path: self.local_path,
+                    s3,
+                })
+            }
+            _ => Err(problems),
+        }
+    }
+}
+
+/// Normalises the key of an endpoint, so the same endpoint can be configured in the environment
+/// and in the config file. Keys are upper case, like in the names of environment variables.
+pub(crate) fn endpoint_key(key: &str) -> String {
+    key.to_ascii_uppercase()
+}
+
+/// Parses a location in the format `latitude longitude`.
+fn parse_loc(input: &str) -> Result<Location, String> {
+    let invalid = || {
+        format!(
+            "LOC '{}' is invalid. Expected latitude and longitude separated by a space.",
+            input
+        )
+    };
+    let (lat, lon) = input
+        .split_whitespace()
+        .map(|part| part.parse::<f64>())
+        .collect_tuple()
+        .ok_or_else(invalid)?;
+    let (lat, lon) = (lat.map_err(|_| invalid())?, lon.map_err(|_| invalid())?);
+    #[cfg(feature = "geoip")]
+    return Ok(Location::new(lat, lon));
+    #[cfg(not(feature = "geoip"))]
+    {
+        let _ = (lat, lon);
+        Ok(())
+    }
+}
+
+/// How the S3 API of an endpoint is addressed, for bucket listings. This is independent of the
+/// public download URL of the endpoint, which may e.g. be a CDN in front of the bucket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct S3Addressing {
+    pub bucket: String,
+    pub region: String,
+    /// URL of the S3 API, without the bucket.
+    pub endpoint: String,
+    /// If true, the bucket is addressed as `{endpoint}/{bucket}`, otherwise as
+    /// `{bucket}.{endpoint host}`.
+    pub path_style: bool,
+    /// Credentials of a private bucket. If set, all requests to the bucket are authenticated
+    /// and downloads use presigned URLs.
+    pub credentials: Option<S3Credentials>,
+}
+
+impl S3Addressing {
+    const DEFAULT_REGION: &'static str = "us-east-1";
+
+    /// Resolves the addressing from the configured values. Values that are not configured are
+    /// derived from the download URL, which is assumed to point to the root of the bucket. URLs
+    /// with an IP address as host are path-style. The region of AWS endpoints is taken from their
+    /// host, other endpoints default to `us-east-1`.
+    /// Returns None, if nothing is configured and either bucket listings are not enabled or the
+    /// bucket can not be derived from the URL.
+    fn resolve(
+        url: &str,
+        bucket: Option<String>,
+        region: Option<String>,
+        list_endpoint: Option<String>,
+        path_style: Option<bool>,
+        credentials: Option<S3Credentials>,
+    ) -> Result<Option<Self>, String> {
+        if credentials.is_some() && !cfg!(feature = "s3_bucket_list") {
+            return Err(
+                "Private buckets are only supported with the s3_bucket_list feature.".to_string(),
+            );
+        }
+        let configured = credentials.is_some()
+            || bucket.is_some()
+            || region.is_some()
+            || list_endpoint.is_some()
+            || path_style.is_some();
+        if !configured && !cfg!(feature = "s3_bucket_list") {
+            return Ok(None);
+        }
+        let (bucket, endpoint, path_style) =
+            match Self::locate_bucket(url, bucket, list_endpoint, path_style) {
+                Ok(located) => located,
+                // Endpoints that are not configured as S3 buckets may be plain mirrors.
+                Err(err) if !configured => {
+                    warn!("{} The endpoint will not be listed.", err);
+                    return Ok(None);
+                }
+                Err(err) => return Err(err),
+            };
+        let endpoint = endpoint.trim_end_matches('/').to_string();
+        let region = match (region, aws_region(&endpoint)) {
+            (Some(region), Some(endpoint_region)) if region != endpoint_region => {
+                return Err(format!(
+                    "REGION '{}' does not match the region '{}' of the endpoint '{}'.",
+                    region, endpoint_region, endpoint
+                ))
+            }
+            (Some(region), _) => region,
+            (None, endpoint_region) => endpoint_region.unwrap_or(Self::DEFAULT_REGION).to_string(),
+        };
+        Ok(Some(Self {
+            bucket,
+            region,
+            endpoint,
+            path_style,
+            credentials,
+        }))
+    }
+
+    /// Returns the bucket, the URL of the S3 API and whether the bucket is addressed path-style.
+    /// Values that are not given are derived from the download URL.
+    fn locate_bucket(
+        url: &str,
+        bucket: Option<String>,
+        list_endpoint: Option<String>,
+        path_style: Option<bool>,
+    ) -> Result<(String, String, bool), String> {
+        let url = url.trim_end_matches('/');
+        let (scheme, host_and_path) = url
+            .split_once("://")
+            .ok_or_else(|| format!("The URL '{}' is not absolute.", url))?;
+        // Buckets can't be subdomains of IP addresses.
+        let path_style = path_style.unwrap_or_else(|| is_ip_host(host_and_path));
+
+        let (bucket, endpoint) = match (bucket, list_endpoint) {
+            (Some(bucket), Some(endpoint)) => (bucket, endpoint),
+            (bucket, None) if path_style => {
+                // https://s3.example.com/bucket
+                let (host, url_bucket) = host_and_path.rsplit_once('/').ok_or_else(|| {
+                    format!(
+                        "The bucket can not be derived from the path-style URL '{}'. \
+                         Configure BUCKET and LIST_ENDPOINT.",
+                        url
+                    )
+                })?;
+                if bucket.as_ref().is_some_and(|bucket| bucket != url_bucket) {
+                    return Err(format!(
+                        "The URL '{}' does not end with the bucket name. \
+                         Configure LIST_ENDPOINT.",
+                        url
+                    ));
+                }
+                (url_bucket.to_string(), format!("{}://{}", scheme, host))
+            }
+            (bucket, None) => {
+                // https://bucket.s3.example.com
+                let (url_bucket, host) = host_and_path
+                    .split_once('.')
+                    .filter(|(_, host)| !host.contains('/'))
+                    .filter(|_| !is_ip_host(host_and_path))
+                    .ok_or_else(|| {
+                        format!(
+                            "The bucket can not be derived from the URL '{}'. \
+                             Configure BUCKET and LIST_ENDPOINT, or PATH_STYLE for path-style URLs.",
+                            url
+                        )
+                    })?;
+                if bucket.as_ref().is_some_and(|bucket| bucket != url_bucket) {
+                    return Err(format!(
+                        "The host of the URL '{}' does not start with the bucket name. \
+                         Configure LIST_ENDPOINT.",
+                        url
+                    ));
+                }
+                (url_bucket.to_string(), format!("{}://{}", scheme, host))
+            }
+            (None, Some(_)) => {
+                return Err(
+                    "LIST_ENDPOINT is configured, but BUCKET is not. Configure BUCKET.".to_string(),
+                )
+            }
+        };
+        Ok((bucket, endpoint, path_style))
+    }
+}
+
+/// Whether the host of a URL without its scheme is an IP address.
+fn is_ip_host(host_and_path: &str) -> bool {
+    let authority = host_and_path.split('/').next().unwrap_or_default();
+    let host = match authority.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next(),
+        None => authority.split(':').next(),
+    };
+    host.is_some_and(|host| host.parse::<IpAddr>().is_ok())
+}
+
+/// Returns the region of an AWS S3 endpoint (`https://s3.eu-west-1.amazonaws.com`), or None for
+/// other endpoints and the global AWS endpoint.
+fn aws_region(endpoint: &str) -> Option<&str> {
+    let host = endpoint
+        .split_once("://")
+        .map_or(endpoint, |(_, rest)| rest);
+    let host = host.split(['/', ':']).next()?;
+    let region = host
+        .strip_suffix(".amazonaws.com")?
+        .strip_prefix("s3")?
+        .trim_start_matches(['.', '-']);
+    let region = region.strip_prefix("dualstack.").unwrap_or(region);
+    // s3-external-1 is an alias of the global endpoint.
+    (!region.is_empty() && region != "external-1").then_some(region)
+}
+
+/// Credentials of a private bucket.
+#[derive(Clone, PartialEq, Eq)]
+pub struct S3Credentials {
+    pub access_key: String,
+    pub secret_key: String,
+    /// How long presigned download URLs are valid, in seconds.
+    pub presign_expiry: u32,
+}
+
+impl S3Credentials {
+    const DEFAULT_PRESIGN_EXPIRY: u32 = 3600;
+    /// The maximum expiry S3 allows for presigned URLs (7 days).
+    const MAX_PRESIGN_EXPIRY: u32 = 604800;
+
+    /// Returns the credentials, or None if the endpoint is public.
+    fn new(
+        access_key: Option<String>,
+        secret_key: Option<String>,
+        presign_expiry: Option<String>,
+    ) -> Result<Option<Self>, String> {
+        let (access_key, secret_key) = match (access_key, secret_key) {
+            (Some(access_key), Some(secret_key)) => (access_key, secret_key),
+            (None, None) if presign_expiry.is_none() => return Ok(None),
+            _ => {
+                return Err(
+                    "Private buckets require both ACCESS_KEY and SECRET_KEY to be configured."
+                        .to_string(),
+                )
+            }
+        };
+        let presign_expiry = match presign_expiry {
+            Some(value) => value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|v| (1..=Self::MAX_PRESIGN_EXPIRY).contains(v))
+                .ok_or_else(|| {
+                    format!(
+                        "PRESIGN_EXPIRY must be a number of seconds between 1 and {}, got '{}'.",
+                        Self::MAX_PRESIGN_EXPIRY,
+                        value
+                    )
+                })?,
+            None => Self::DEFAULT_PRESIGN_EXPIRY,
+        };
+        Ok(Some(Self {
+            access_key,
+            secret_key,
+            presign_expiry,
+        }))
+    }
+}
+
+impl fmt::Debug for S3Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("S3Credentials")
+            .field("access_key", &self.access_key)
+            .field("secret_key", &"<redacted>")
+            .field("presign_expiry", &self.presign_expiry)
+            .finish()
+    }
+}
+
+struct HomeUrl {}
+
+impl SimpleConfig for HomeUrl {
+    const VAR_NAME: &'static str = "DEPBOX_HOME_URL";
+}
+
+struct SelfName {}
+
+impl SimpleConfig for SelfName {
+    const VAR_NAME: &'static str = "DEPBOX_SELF_NAME";
+}
+
+impl SelfName {
+    pub fn default_value() -> String {
+        "Deposit Box".to_string()
+    }
+}
+
+struct Theme {}
+
+impl SimpleConfig for Theme {
+    const VAR_NAME: &'static str = "DEPBOX_THEME";
+}
+
+struct ReleaseInfoEnable {}
+
+impl SimpleConfigBool for ReleaseInfoEnable {
+    const VAR_NAME: &'static str = "DEPBOX_RELEASE_INFO_ENABLE";
+}
+
+struct ReleaseInfoDomain {}
+
+impl SimpleConfig for ReleaseInfoDomain {
+    const VAR_NAME: &'static str = "DEPBOX_RELEASE_INFO_DOMAIN";
+}
+
+struct BannerEnable {}
+
+impl SimpleConfigBool for BannerEnable {
+    const VAR_NAME: &'static str = "DEPBOX_BANNER_ENABLE";
+}
+
+struct AdminToken {}
+
+impl SimpleConfig for AdminToken {
+    const VAR_NAME: &'static str = "DEPBOX_ADMIN_TOKEN";
+}
+
+struct HealthCheckInterval {}
+
+impl SimpleConfig for HealthCheckInterval {
+    const VAR_NAME: &'static str = "DEPBOX_HEALTH_CHECK_INTERVAL";
+}
+
+impl HealthCheckInterval {
+    const DEFAULT_SECS: u64 = 60;
+
+    /// Returns the interval between health checks, or None if they are disabled (set to 0).
+    pub fn get_duration(vars: &ConfigVars) -> Result<Option<Duration>, String> {
+        let secs = match Self::get_checked(vars) {
+            Ok(v) => v.trim().parse().map_err(|_| {
+                format!(
+                    "Invalid value for {}: '{}'. Expected a number of seconds.",
+                    Self::VAR_NAME,
+                    v
+                )
+            })?,
+            Err(_) => Self::DEFAULT_SECS,
+        };
+        Ok((secs > 0).then(|| Duration::from_secs(secs)))
+    }
+}
+
+struct MaxmindDbPath {}
+
+impl SimpleConfig for MaxmindDbPath {
+    const VAR_NAME: &'static str = "DEPBOX_MAXMINDDB_PATH";
+}
+
+struct GithubToken {}
+
+impl SimpleConfig for GithubToken {
+    const VAR_NAME: &'static str = "DEPBOX_GITHUB_TOKEN";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Resolves the addressing of a public bucket. Without the s3_bucket_list feature, at least
+    /// one value has to be configured.
+    fn resolve(
+        url: &str,
+        bucket: Option<&str>,
+        region: Option<&str>,
+        list_endpoint: Option<&str>,
+        path_style: Option<bool>,
+    ) -> Result<S3Addressing, String> {
+        S3Addressing::resolve(
+            url,
+            bucket.map(Into::into),
+            region.map(Into::into),
+            list_endpoint.map(Into::into),
+            path_style,
+            None,
+        )
+        .map(|addressing| addressing.expect("addressing is configured"))
+    }
+
+    fn addressing(bucket: &str, region: &str, endpoint: &str, path_style: bool) -> S3Addressing {
+        S3Addressing {
+            bucket: bucket.to_string(),
+            region: region.to_string(),
+            endpoint: endpoint.to_string(),
+            path_style,
+            credentials: None,
+        }
+    }
+
+    #[test]
+    fn virtual_hosted_url() {
+        assert_eq!(
+            resolve(
+                "https://releases.s3.example.com/",
+                None,
+                Some("eu"),
+                None,
+                None
+            ),
+            Ok(addressing(
+                "releases",
+                "eu",
+                "https://s3.example.com",
+                false
+            ))
+        );
+        assert_eq!(
+            resolve(
+                "http://releases.minio.local:9000",
+                None,
+                Some("eu"),
+                None,
+                None
+            ),
+            Ok(addressing(
+                "releases",
+                "eu",
+                "http://minio.local:9000",
+                false
+            ))
+        );
+    }
+
+    #[test]
+    fn path_style_url() {
+        assert_eq!(
+            resolve(
+                "https://s3.example.com/releases/",
+                None,
+                None,
+                None,
+                Some(true)
+            ),
+            Ok(addressing(
+                "releases",
+                S3Addressing::DEFAULT_REGION,
+                "https://s3.example.com",
+                true
+            ))
+        );
+    }
+
+    #[test]
+    fn ip_hosts_are_path_style() {
+        assert_eq!(
+            resolve(
+                "http://127.0.0.1:9000/releases",
+                None,
+                Some("eu"),
+                None,
+                None
+            ),
+            Ok(addressing("releases", "eu", "http://127.0.0.1:9000", true))
+        );
+        assert_eq!(
+            resolve("http://[::1]:9000/releases", None, Some("eu"), None, None),
+            Ok(addressing("releases", "eu", "http://[::1]:9000", true))
+        );
+        let err = resolve(
+            "http://127.0.0.1:9000/releases",
+            None,
+            None,
+            None,
+            Some(false),
+        )
+        .unwrap_err();
+        assert!(err.contains("can not be derived"), "{}", err);
+    }
+
+    #[test]
+    fn configured_bucket_and_list_endpoint() {
+        assert_eq!(
+            resolve(
+                "https://cdn.example.com",
+                Some("releases"),
+                Some("eu"),
+                Some("https://s3.example.com/"),
+                None
+            ),
+            Ok(addressing(
+                "releases",
+                "eu",
+                "https://s3.example.com",
+                false
+            ))
+        );
+        let err = resolve(
+            "https://cdn.example.com",
+            None,
+            None,
+            Some("https://s3.example.com"),
+            None,
+        )
+        .unwrap_err();
+        assert!(err.contains("BUCKET is not"), "{}", err);
+    }
+
+    #[test]
+    fn bucket_must_match_url() {
+        let err = resolve(
+            "https://releases.s3.example.com",
+            Some("other"),
+            None,
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(
+            err.contains("does not start with the bucket name"),
+            "{}",
+            err
+        );
+        let err = resolve(
+            "https://s3.example.com/releases",
+            Some("other"),
+            None,
+            None,
+            Some(true),
+        )
+        .unwrap_err();
+        assert!(err.contains("does not end with the bucket name"), "{}", err);
+        assert_eq!(
+            resolve(
+                "https://releases.s3.example.com",
+                Some("releases"),
+                Some("eu"),
+                None,
+                None
+            ),
+            Ok(addressing(
+                "releases",
+                "eu",
+                "https://s3.example.com",
+                false
+            ))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "s3_bucket_list")]
+    fn region_of_aws_endpoints() {
+        assert_eq!(
+            resolve(
+                "https://releases.s3.eu-west-1.amazonaws.com",
+                None,
+                None,
+                None,
+                None
+            ),
+            Ok(addressing(
+                "releases",
+                "eu-west-1",
+                "https://s3.eu-west-1.amazonaws.com",
+                false
+            ))
+        );
+        assert_eq!(
+            resolve("https://releases.s3.amazonaws.com", None, None, None, None)
+                .map(|addressing| addressing.region),
+            Ok(S3Addressing::DEFAULT_REGION.to_string())
+        );
+        let err = resolve(
+            "https://releases.s3.eu-west-1.amazonaws.com",
+            None,
+            Some("us-east-1"),
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(
+            err.contains("REGION 'us-east-1' does not match the region 'eu-west-1'"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn aws_region_of_hosts() {
+        assert_eq!(
+            aws_region("https://s3.eu-west-1.amazonaws.com"),
+            Some("eu-west-1")
+        );
+        assert_eq!(
+            aws_region("https://s3-eu-west-1.amazonaws.com"),
+            Some("eu-west-1")
+        );
+        assert_eq!(
+            aws_region("https://s3.dualstack.eu-west-1.amazonaws.com:443"),
+            Some("eu-west-1")
+        );
+        assert_eq!(aws_region("https://s3.amazonaws.com"), None);
+        assert_eq!(aws_region("https://s3-external-1.amazonaws.com"), None);
+        assert_eq!(aws_region("https://s3.example.com"), None);
+    }
+
+    #[test]
+    fn plain_mirrors_have_no_listing() {
+        for url in [
+            "https://mirror.example.com/skytemple",
+            "http://localhost:8000",
+            "http://127.0.0.1:8000",
+            "/releases",
+        ] {
+            assert_eq!(
+                S3Addressing::resolve(url, None, None, None, None, None),
+                Ok(None),
+                "{}",
+                url
+            );
+        }
+        let endpoint = RawEndpoint {
+            url: Some("https://mirror.example.com/skytemple".to_string()),
+            path_style: None,
+            ..raw_endpoint()
+        }
+        .resolve("mirror")
+        .unwrap();
+        assert_eq!(endpoint.s3, None);
+    }
+
+    #[test]
+    fn configured_mirrors_must_be_buckets() {
+        let err = resolve(
+            "https://mirror.example.com/skytemple",
+            None,
+            Some("eu"),
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(err.contains("can not be derived"), "{}", err);
+    }
+
+    #[test]
+    fn relative_url_fails() {
+        let err = resolve("/releases", None, None, None, Some(true)).unwrap_err();
+        assert!(err.contains("is not absolute"), "{}", err);
+    }
+
+    fn raw_endpoint() -> RawEndpoint {
+        RawEndpoint {
+            display_name: Some("Europe".to_string()),
+            url: Some("https://releases.s3.eu-west-1.amazonaws.com".to_string()),
+            location: Some("50.1 8.7".to_string()),
+            path_style: Some("0".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn raw_endpoint_resolves_s3_addressing() {
+        let endpoint = raw_endpoint().resolve("eu").unwrap();
+        assert_eq!(endpoint.key, "eu");
+        assert_eq!(endpoint.url, "https://releases.s3.eu-west-1.amazonaws.com");
+        assert_eq!(
+            endpoint.s3,
+            Some(addressing(
+                "releases",
+                "eu-west-1",
+                "https://s3.eu-west-1.amazonaws.com",
+                false
+            ))
+        );
+        let endpoint = RawEndpoint {
+            url: Some("http://127.0.0.1:9000/releases".to_string()),
+            region: Some("eu".to_string()),
+            path_style: None,
+            ..raw_endpoint()
+        }
+        .resolve("local")
+        .unwrap();
+        assert_eq!(
+            endpoint.s3,
+            Some(addressing("releases", "eu", "http://127.0.0.1:9000", true))
+        );
+    }
+
+    #[test]
+    fn raw_endpoint_reports_addressing_errors() {
+        let problems = RawEndpoint {
+            bucket: Some("other".to_string()),
+            ..raw_endpoint()
+        }
+        .resolve("eu")
+        .unwrap_err();
+        assert_eq!(problems.len(), 1);
+        assert!(
+            problems[0].contains("does not start with the bucket name"),
+            "{:?}",
+            problems
+        );
+        let problems = RawEndpoint {
+            region: Some("us-east-1".to_string()),
+            ..raw_endpoint()
+        }
+        .resolve("eu")
+        .unwrap_err();
+        assert!(
+            problems[0].contains("does not match the region"),
+            "{:?}",
+            problems
+        );
+    }
+
+    #[test]
+    fn raw_endpoint_reports_all_problems() {
+        let problems = RawEndpoint::default().resolve("eu").unwrap_err();
+        assert_eq!(
+            problems,
+            [
+                "DISPLAY_NAME is missing.",
+                "URL (or PATH for local endpoints) is missing.",
+                "LOC is missing."
+            ]
+        );
+    }
+
+    #[test]
+    fn local_endpoint_has_no_addressing() {
+        let endpoint = RawEndpoint {
+            url: None,
+            local_path: Some(PathBuf::from("/srv/releases")),
+            bucket: Some("ignored".to_string()),
+            ..raw_endpoint()
+        }
+        .resolve("local")
+        .unwrap();
+        assert_eq!(endpoint.url, format!("{}/local", LOCAL_FILES_ROUTE));
+        assert_eq!(endpoint.s3, None);
+    }
+
+    fn env_vars(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn endpoints_of_file_and_environment_are_merged() {
+        let file_endpoints = IndexMap::from([
+            (
+                endpoint_key("eu"),
+                RawEndpoint {
+                    url: None,
+                    path_style: None,
+                    ..raw_endpoint()
+                },
+            ),
+            (endpoint_key("Local"), raw_endpoint()),
+        ]);
+        let endpoints = Endpoints::do_load(
+            file_endpoints,
+            env_vars(&[
+                ("DEPBOX_S3_ENDPOINT__0__EU__URL", "http://127.0.0.1:9000/eu"),
+                ("DEPBOX_S3_ENDPOINT__1__US__DISPLAY_NAME", "US"),
+                ("DEPBOX_S3_ENDPOINT__1__US__URL", "http://127.0.0.1:9000/us"),
+                ("DEPBOX_S3_ENDPOINT__1__US__LOC", "40.7 -74.0"),
+                ("DEPBOX_HOME_URL", "https://example.com"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            endpoints.iter().map(|e| e.key.as_str()).collect::<Vec<_>>(),
+            ["EU", "LOCAL", "US"]
+        );
+        assert_eq!(endpoints[0].url, "http://127.0.0.1:9000/eu");
+        assert_eq!(endpoints[0].display_name, "Europe");
+    }
+
+    #[test]
+    fn environment_takes_precedence_over_config_file() {
+        let vars = ConfigVars {
+            file: HashMap::from([
+                ("PATH", "/from/file".to_string()),
+                ("DEPBOX_TEST_ONLY_IN_FILE", "file".to_string()),
+            ]),
+        };
+        assert_eq!(vars.var("PATH"), env::var("PATH"));
+        assert_eq!(vars.var("DEPBOX_TEST_ONLY_IN_FILE"), Ok("file".to_string()));
+        assert_eq!(
+            vars.var("DEPBOX_TEST_NOT_CONFIGURED"),
+            Err(env::VarError::NotPresent)
+        );
+    }
+}
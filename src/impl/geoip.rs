@@ -42,7 +42,7 @@ pub fn sort_by_location<S: AsRef<[u8]>>(
 }
 
 pub fn find_best_location<'a, S: AsRef<[u8]>>(
-    endpoints: &'a [Endpoint],
+    endpoints: &[&'a Endpoint],
     geoipdb: &maxminddb::Reader<S>,
     ip_addr: IpAddr,
 ) -> &'a Endpoint {
@@ -62,13 +62,14 @@ pub fn find_best_location<'a, S: AsRef<[u8]>>(
                         .abs() as i64
                 })
                 .next()
+                .copied()
                 .unwrap(),
             _ => {
                 debug!(
                     "Failed to find location for IP address: {:?} has no location information.",
                     city
                 );
-                &endpoints[0]
+                endpoints[0]
             }
         },
         Err(err) => {
@@ -76,7 +77,7 @@ pub fn find_best_location<'a, S: AsRef<[u8]>>(
                 "Failed to find location for IP address: {}: {}",
                 ip_addr, err
             );
-            &endpoints[0]
+            endpoints[0]
         }
     }
 }
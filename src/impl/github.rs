@@ -1,6 +1,7 @@
 use std::sync::OnceLock;
 
 use cached::proc_macro::cached;
+use cached::Cached;
 use octocrab::models::repos::Release;
 use octocrab::models::workflows::Run;
 use octocrab::models::RunId;
@@ -34,6 +35,26 @@ impl GithubClient {
         })
     }
 
+    /// Drops the cached releases of the given project (`org/repo`), or of all projects if None.
+    pub async fn invalidate_releases(project: Option<&str>) {
+        let mut cache = CACHED_FETCH_GITHUB_RELEASE.lock().await;
+        match project {
+            None => cache.cache_clear(),
+            Some(project) => {
+                let prefix = format!("{}::", project);
+                let keys: Vec<String> = cache
+                    .get_store()
+                    .keys()
+                    .filter(|k| k.starts_with(&prefix))
+                    .cloned()
+                    .collect();
+                for key in keys {
+                    cache.cache_remove(&key);
+                }
+            }
+        }
+    }
+
     pub async fn fetch_release(
         &self,
         org: &str,
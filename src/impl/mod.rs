@@ -1,4 +1,6 @@
+pub mod admin;
 pub mod artifacttype;
+pub mod channel;
 pub mod config;
 #[cfg(feature = "geoip")]
 pub mod geoip;
@@ -7,9 +9,12 @@ mod github;
 mod markdown;
 pub mod nightly;
 mod pre_release;
+pub mod release_date;
 pub mod release_map;
 pub mod routes;
 pub mod storage;
 pub mod templates;
 #[cfg(feature = "amazon_translate")]
 mod translate;
+pub mod validate;
+pub mod version_scheme;
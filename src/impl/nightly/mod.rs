@@ -45,6 +45,13 @@ pub struct NightlyConfig {
     downloads: IndexMap<ArtifactKey, DownloadSpec>,
 }
 
+impl NightlyConfig {
+    /// The downloads of the nightly build by artifact key.
+    pub fn downloads(&self) -> &IndexMap<ArtifactKey, DownloadSpec> {
+        &self.downloads
+    }
+}
+
 async fn nightly_artifacts_collect(
     product_name: &str,
     nightly_config: &NightlyConfig,
@@ -68,13 +75,21 @@ async fn nightly_artifacts_collect(
                         .map(ToString::to_string)
                         .map(Into::into),
                     display_name: artifact_info.display_name().clone_owned(),
+                    label: download_spec.label().map(|label| label.to_string().into()),
                     modified_date: None,
                     file_size: None,
                     urls,
+                    mirrors: BTreeMap::new(),
                     extra_info_markdown: artifact_info
                         .extra_info_markdown()
                         .map(|s| s.to_string())
                         .map(Into::into),
+                    checksums: download_spec
+                        .checksums()
+                        .iter()
+                        .map(|(algorithm, value)| (algorithm.into(), value.to_string().into()))
+                        .collect(),
+                    signature: None,
                 })
             }
             Err(err) => log::warn!(
@@ -1,4 +1,21 @@
-use crate::r#impl::storage::PreReleasePatternEntry;
+use std::borrow::Cow;
+
+use crate::r#impl::storage::{PreReleasePatternEntry, Product};
+
+/// The pre-release patterns of a product: Its own patterns, followed by the global patterns
+/// unless the product overrides them.
+pub fn product_pre_release_patterns<'a>(
+    product: &'a Product,
+    global: &'a [PreReleasePatternEntry],
+) -> Cow<'a, [PreReleasePatternEntry]> {
+    if product.override_pre_release_patterns || global.is_empty() {
+        Cow::Borrowed(&product.pre_release_patterns)
+    } else if product.pre_release_patterns.is_empty() {
+        Cow::Borrowed(global)
+    } else {
+        Cow::Owned([product.pre_release_patterns.as_slice(), global].concat())
+    }
+}
 
 pub fn parse_pre_release<'a>(
     version: &str,
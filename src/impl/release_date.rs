@@ -0,0 +1,211 @@
+use std::fmt;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Deserializer};
+
+/// Formats of dates with a time that are accepted besides RFC 3339. Times are in UTC.
+const DATE_TIME_FORMATS: &[&str] = &["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"];
+/// Formats of dates without a time. ISO 8601 first, followed by the formats of older product
+/// configurations.
+const DATE_FORMATS: &[&str] = &[
+    "%Y-%m-%d",
+    "%d.%m.%Y",
+    "%Y/%m/%d",
+    "%B %d, %Y",
+    "%b %d, %Y",
+    "%d %B %Y",
+];
+
+/// The date of a release, as given in `products.yml`.
+///
+/// Dates are written in ISO 8601 (`2023-01-02` or `2023-01-02T15:04:05Z`), or in one of the formats
+/// of older configurations (`02.01.2023`, `2023/01/02`, `January 2, 2023`). Dates in other formats
+/// can't be sorted or shown relative to now, but are kept so they can still be shown as written.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ReleaseDate {
+    #[default]
+    Missing,
+    Date(NaiveDate),
+    DateTime(DateTime<Utc>),
+    Unparsed(String),
+}
+
+impl ReleaseDate {
+    pub fn parse(raw: &str) -> Self {
+        let raw = raw.trim();
+        if raw.is_empty() {
+            return ReleaseDate::Missing;
+        }
+        if let Ok(date_time) = DateTime::parse_from_rfc3339(raw) {
+            return ReleaseDate::DateTime(date_time.with_timezone(&Utc));
+        }
+        if let Some(date_time) = DATE_TIME_FORMATS
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(raw, format).ok())
+        {
+            return ReleaseDate::DateTime(date_time.and_utc());
+        }
+        match DATE_FORMATS
+            .iter()
+            .find_map(|format| NaiveDate::parse_from_str(raw, format).ok())
+        {
+            Some(date) => ReleaseDate::Date(date),
+            None => ReleaseDate::Unparsed(raw.to_string()),
+        }
+    }
+
+    pub fn is_missing(&self) -> bool {
+        matches!(self, ReleaseDate::Missing)
+    }
+
+    /// The date as a point in time. Dates without a time are at midnight UTC.
+    pub fn date_time(&self) -> Option<DateTime<Utc>> {
+        match self {
+            ReleaseDate::Date(date) => Some(date.and_time(Default::default()).and_utc()),
+            ReleaseDate::DateTime(date_time) => Some(*date_time),
+            ReleaseDate::Missing | ReleaseDate::Unparsed(_) => None,
+        }
+    }
+
+    /// Unix timestamp of `date_time`, for the `reltime` and `fulltime` template filters.
+    pub fn timestamp(&self) -> Option<i64> {
+        self.date_time().map(|date_time| date_time.timestamp())
+    }
+
+    /// The date in ISO 8601, for machine-readable output (`<time datetime="...">`, feeds).
+    pub fn iso(&self) -> Option<String> {
+        match self {
+            ReleaseDate::Date(date) => Some(date.format("%Y-%m-%d").to_string()),
+            ReleaseDate::DateTime(date_time) => {
+                Some(date_time.to_rfc3339_opts(SecondsFormat::Secs, true))
+            }
+            ReleaseDate::Missing | ReleaseDate::Unparsed(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for ReleaseDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReleaseDate::Missing => Ok(()),
+            ReleaseDate::Date(date) => write!(f, "{}", date.format("%Y-%m-%d")),
+            ReleaseDate::DateTime(date_time) => {
+                write!(f, "{}", date_time.format("%Y-%m-%d %H:%M:%S UTC"))
+            }
+            ReleaseDate::Unparsed(raw) => write!(f, "{}", raw),
+        }
+    }
+}
+
+impl From<DateTime<Utc>> for ReleaseDate {
+    fn from(date_time: DateTime<Utc>) -> Self {
+        ReleaseDate::DateTime(date_time)
+    }
+}
+
+impl<'de> Deserialize<'de> for ReleaseDate {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer).map(|raw| ReleaseDate::parse(&raw))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> ReleaseDate {
+        ReleaseDate::Date(NaiveDate::from_ymd_opt(year, month, day).unwrap())
+    }
+
+    fn date_time(year: i32, month: u32, day: u32, h: u32, m: u32, s: u32) -> ReleaseDate {
+        ReleaseDate::DateTime(Utc.with_ymd_and_hms(year, month, day, h, m, s).unwrap())
+    }
+
+    #[test]
+    fn parses_iso_dates() {
+        assert_eq!(ReleaseDate::parse("2023-01-02"), date(2023, 1, 2));
+        assert_eq!(
+            ReleaseDate::parse("2023-01-02T15:04:05Z"),
+            date_time(2023, 1, 2, 15, 4, 5)
+        );
+        assert_eq!(
+            ReleaseDate::parse("2023-01-02T15:04:05+02:00"),
+            date_time(2023, 1, 2, 13, 4, 5)
+        );
+    }
+
+    #[test]
+    fn parses_legacy_dates() {
+        for raw in [
+            "02.01.2023",
+            "2.1.2023",
+            "2023/01/02",
+            "January 2, 2023",
+            "January 02, 2023",
+            "Jan 2, 2023",
+            "2 January 2023",
+            " 2023-01-02 ",
+        ] {
+            assert_eq!(ReleaseDate::parse(raw), date(2023, 1, 2), "{}", raw);
+        }
+    }
+
+    #[test]
+    fn parses_legacy_date_times() {
+        assert_eq!(
+            ReleaseDate::parse("2023-01-02 15:04:05"),
+            date_time(2023, 1, 2, 15, 4, 5)
+        );
+        assert_eq!(
+            ReleaseDate::parse("2023-01-02 15:04"),
+            date_time(2023, 1, 2, 15, 4, 0)
+        );
+        assert_eq!(
+            ReleaseDate::parse("2023-01-02T15:04:05"),
+            date_time(2023, 1, 2, 15, 4, 5)
+        );
+    }
+
+    #[test]
+    fn keeps_unparsed_dates_as_written() {
+        for raw in ["Q1 2023", "Summer 2022", "2023-13-01", "01/02/2023", "soon"] {
+            let parsed = ReleaseDate::parse(raw);
+            assert_eq!(parsed, ReleaseDate::Unparsed(raw.to_string()));
+            assert_eq!(parsed.to_string(), raw);
+            assert_eq!(parsed.date_time(), None);
+            assert_eq!(parsed.iso(), None);
+        }
+    }
+
+    #[test]
+    fn empty_dates_are_missing() {
+        assert!(ReleaseDate::parse("").is_missing());
+        assert!(ReleaseDate::parse("   ").is_missing());
+        assert_eq!(ReleaseDate::parse("").to_string(), "");
+    }
+
+    #[test]
+    fn deserializes_from_yaml() {
+        let parse = |yaml: &str| serde_yaml::from_str::<ReleaseDate>(yaml).unwrap();
+        assert_eq!(parse("2023-01-02"), date(2023, 1, 2));
+        assert_eq!(parse("\"02.01.2023\""), date(2023, 1, 2));
+        assert_eq!(
+            parse("Early 2023"),
+            ReleaseDate::Unparsed("Early 2023".to_string())
+        );
+    }
+
+    #[test]
+    fn formats_dates() {
+        assert_eq!(date(2023, 1, 2).to_string(), "2023-01-02");
+        assert_eq!(date(2023, 1, 2).iso().as_deref(), Some("2023-01-02"));
+        let with_time = date_time(2023, 1, 2, 15, 4, 5);
+        assert_eq!(with_time.to_string(), "2023-01-02 15:04:05 UTC");
+        assert_eq!(with_time.iso().as_deref(), Some("2023-01-02T15:04:05Z"));
+    }
+}
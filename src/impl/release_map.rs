@@ -1,16 +1,14 @@
 use crate::r#impl::pre_release::parse_pre_release;
-use crate::r#impl::storage::{PreReleasePatternEntry, VersionInfo};
+use crate::r#impl::release_date::ReleaseDate;
+use crate::r#impl::storage::{PreReleasePatternEntry, SupportStatus, VersionInfo};
+use crate::r#impl::version_scheme::VersionScheme;
 use indexmap::IndexMap;
 use serde::de::{MapAccess, Visitor};
 use serde::{Deserialize, Deserializer};
 use std::borrow::Cow;
-#[cfg(feature = "sort_versions")]
-use std::cmp::Ordering;
 use std::fmt;
 use std::hash::BuildHasher;
 use std::marker::PhantomData;
-#[cfg(feature = "sort_versions")]
-use version_compare::Cmp;
 
 /// A struct that contains a name of a version and whether or not it is the latest version
 /// for a product.
@@ -18,29 +16,64 @@ pub struct VersionListEntry<'a> {
     pub name: &'a str,
     pub is_latest: bool,
     pub is_pre_release: bool,
+    pub date: &'a ReleaseDate,
+    pub support: Option<SupportStatus>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
-/// A map of versions. The interior index map is always sorted by version number (best-effort, see
-/// `version-compare` crate.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+/// A map of versions. The interior index map is sorted from oldest to newest version, by the
+/// version scheme of the product (see `ReleaseMap::sort`).
 pub struct ReleaseMap(
     #[serde(deserialize_with = "ReleaseMap::serde_deserialize")] IndexMap<String, VersionInfo>,
 );
 
 impl ReleaseMap {
-    /// Returns a map of all versions, sorted by version number.
+    /// Returns a map of all versions, sorted from oldest to newest.
     #[inline]
     pub fn map(&self) -> &IndexMap<String, VersionInfo> {
         &self.0
     }
 
+    /// Sorts the versions from oldest to newest by the version scheme. Versions that are equal
+    /// under the scheme keep their order.
+    pub fn sort(&mut self, scheme: VersionScheme) {
+        self.0
+            .sort_by(|a_name, a, b_name, b| scheme.compare((a_name, a), (b_name, b)));
+    }
+
+    /// Keeps only the versions for which `keep` returns true.
+    pub fn retain(&mut self, mut keep: impl FnMut(&str, &VersionInfo) -> bool) {
+        self.0.retain(|name, info| keep(name, info));
+    }
+
+    /// Finds a version by a name from a URL and returns its canonical name. Besides the name
+    /// itself, names that only differ in case and the version's aliases match.
+    pub fn find(&self, name: &str) -> Option<&str> {
+        if let Some((canonical, _)) = self.0.get_key_value(name) {
+            return Some(canonical);
+        }
+        let found = self
+            .0
+            .keys()
+            .find(|canonical| canonical.eq_ignore_ascii_case(name))
+            .or_else(|| {
+                self.0.iter().find_map(|(canonical, info)| {
+                    info.aliases
+                        .iter()
+                        .any(|alias| alias.eq_ignore_ascii_case(name))
+                        .then_some(canonical)
+                })
+            });
+        found.map(String::as_str)
+    }
+
     /// Returns an item from the map as a named version.
     #[inline]
     pub fn get<'a>(&'a self, k: &'a str) -> Option<NamedVersion<'a>> {
         self.0.get(k).map(move |v| NamedVersion(k.into(), v.into()))
     }
 
-    // Returns an iterator over the versions, that provides the names of the versions,
+    // Returns an iterator over the listed versions, that provides the names of the versions,
     // whether they are the latest version and whether they are a pre-release.
     //
     // The result iterator is sorted from highest version to lowest!
@@ -50,39 +83,54 @@ impl ReleaseMap {
     ) -> impl IntoIterator<Item = VersionListEntry<'a>> + 'a {
         let mut had_latest = false;
         self.0
-            .keys()
+            .iter()
             .rev()
-            .map(|name| match parse_pre_release(name, pre_release_patterns) {
-                None => match had_latest {
-                    true => VersionListEntry {
-                        name,
-                        is_latest: false,
-                        is_pre_release: false,
-                    },
-                    false => {
-                        had_latest = true;
-                        VersionListEntry {
+            .filter(|(_, info)| info.is_listed())
+            .map(
+                |(name, info)| match parse_pre_release(name, pre_release_patterns) {
+                    None => match had_latest {
+                        true => VersionListEntry {
                             name,
-                            is_latest: true,
+                            is_latest: false,
                             is_pre_release: false,
+                            date: &info.date,
+                            support: info.support_status(),
+                        },
+                        false => {
+                            had_latest = true;
+                            VersionListEntry {
+                                name,
+                                is_latest: true,
+                                is_pre_release: false,
+                                date: &info.date,
+                                support: info.support_status(),
+                            }
                         }
-                    }
-                },
-                Some(_) => VersionListEntry {
-                    name,
-                    is_latest: false,
-                    is_pre_release: true,
+                    },
+                    Some(_) => VersionListEntry {
+                        name,
+                        is_latest: false,
+                        is_pre_release: true,
+                        date: &info.date,
+                        support: info.support_status(),
+                    },
                 },
-            })
+            )
             .collect::<Vec<_>>()
     }
 
-    /// Returns the latest version or None if the map is empty.
-    pub fn latest(&self, pre_release_patterns: &[PreReleasePatternEntry]) -> Option<NamedVersion> {
+    /// Returns the latest version or None if there is no listed version that is not a
+    /// pre-release.
+    pub fn latest(
+        &self,
+        pre_release_patterns: &[PreReleasePatternEntry],
+    ) -> Option<NamedVersion<'_>> {
         self.0
             .iter()
             .rev()
-            .find(|(name, _)| parse_pre_release(name, pre_release_patterns).is_none())
+            .find(|(name, info)| {
+                info.is_listed() && parse_pre_release(name, pre_release_patterns).is_none()
+            })
             .map(Into::into)
     }
 
@@ -94,6 +142,13 @@ impl ReleaseMap {
     }
 }
 
+impl FromIterator<(String, VersionInfo)> for ReleaseMap {
+    /// Collects the versions into a map, in the given order.
+    fn from_iter<T: IntoIterator<Item = (String, VersionInfo)>>(iter: T) -> Self {
+        Self(IndexMap::from_iter(iter))
+    }
+}
+
 impl IntoIterator for ReleaseMap {
     type Item = (String, VersionInfo);
     type IntoIter = <IndexMap<String, VersionInfo> as IntoIterator>::IntoIter;
@@ -121,32 +176,12 @@ where
         A: MapAccess<'de>,
     {
         let capacity = map.size_hint().unwrap_or(0);
-
-        #[cfg(feature = "sort_versions")]
-        {
-            // TODO: This is basically what `Itertools::sort_by` does, but ideally
-            //       you wouldn't want to allocate a full Vec if possible.
-            let mut v: Vec<(String, VersionInfo)> = Vec::with_capacity(capacity);
-            while let Some(val) = map.next_entry()? {
-                v.push(val);
-            }
-            v.sort_by(|(k1, _), (k2, _)| {
-                version_compare::compare(&k1, &k2)
-                    .unwrap_or(Cmp::Lt)
-                    .ord()
-                    .unwrap_or(Ordering::Less)
-            });
-            Ok(IndexMap::from_iter(v))
-        }
-        #[cfg(not(feature = "sort_versions"))]
-        {
-            let mut imap: IndexMap<String, VersionInfo, S> =
-                IndexMap::with_capacity_and_hasher(capacity, S::default());
-            while let Some((k, v)) = map.next_entry()? {
-                imap.insert(k, v);
-            }
-            Ok(imap)
+        let mut imap: IndexMap<String, VersionInfo, S> =
+            IndexMap::with_capacity_and_hasher(capacity, S::default());
+        while let Some((k, v)) = map.next_entry()? {
+            imap.insert(k, v);
         }
+        Ok(imap)
     }
 }
 
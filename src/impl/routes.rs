@@ -1,10 +1,13 @@
 use std::borrow::Cow;
 use std::io::Cursor;
 use std::net::IpAddr;
+use std::path::PathBuf;
 
 use async_trait::async_trait;
 use cached::proc_macro::cached;
+use indexmap::IndexMap;
 use log::{error, warn};
+use rocket::fs::NamedFile;
 use rocket::http::uri::Host;
 use rocket::http::{ContentType, Header, Status};
 use rocket::outcome::Outcome::{Forward, Success};
@@ -16,18 +19,21 @@ use rocket_accept_language::AcceptLanguage;
 use crate::r#impl::artifacttype::{
     artifacts_collect, artifacts_describe, NightlyArtifactResponder,
 };
+use crate::r#impl::channel::{Channels, LATEST_PREFIX};
 use crate::r#impl::config::Config;
 use crate::r#impl::markdown::markdown;
 use crate::r#impl::nightly::{do_get_nightly, do_get_nightly_artifact};
-use crate::r#impl::pre_release::parse_pre_release;
+use crate::r#impl::pre_release::{parse_pre_release, product_pre_release_patterns};
+use crate::r#impl::release_date::ReleaseDate;
 use crate::r#impl::release_map::NamedVersion;
-use crate::r#impl::storage::{Product, ProductsConfig};
+use crate::r#impl::storage::{PreReleasePatternEntry, Product, ProductsConfig};
 use crate::r#impl::templates::*;
 #[cfg(feature = "amazon_translate")]
 use crate::r#impl::translate::*;
 
 type Response<T> = Result<T, Status>;
 const LATEST: &str = "latest";
+const NIGHTLY: &str = "nightly";
 
 #[get("/")]
 pub async fn get_root<'a>(
@@ -37,33 +43,84 @@ pub async fn get_root<'a>(
     if is_release_info(config, host) {
         Err(Status::NotFound)
     } else {
-        let products = get_storage_config(config).await?.products;
+        let storage_config = get_storage_config(config).await?;
         Ok(TemplateProducts {
             self_name: config.self_name().into(),
             theme_name: config.theme().into(),
             home_url: config.home_url().into(),
             default_endpoint_url: config.default_endpoint_url().into(),
-            products,
+            categories: product_categories(
+                storage_config.products,
+                &storage_config.pre_release_patterns,
+            ),
         })
     }
 }
 
+/// Groups the products by category for the products page. The categories are in the order they
+/// first appear in the configuration, after the products without a category. In each category,
+/// featured products come first, then the products are ordered by sort weight.
+fn product_categories(
+    products: IndexMap<String, Product>,
+    pre_release_patterns: &[PreReleasePatternEntry],
+) -> Vec<ProductCategory> {
+    let mut categories: IndexMap<Option<String>, Vec<ProductListEntry>> = IndexMap::new();
+    categories.insert(None, Vec::new());
+    for (key, product) in products {
+        let pre_release_patterns = product_pre_release_patterns(&product, pre_release_patterns);
+        let (latest_version, latest_date) = match product.versions.latest(&pre_release_patterns) {
+            Some(latest) => (Some(latest.name().to_string()), latest.info().date.clone()),
+            None => (None, ReleaseDate::Missing),
+        };
+        categories
+            .entry(product.category.clone())
+            .or_default()
+            .push(ProductListEntry {
+                key,
+                product,
+                latest_version,
+                latest_date,
+            });
+    }
+    categories
+        .into_iter()
+        .filter(|(_, products)| !products.is_empty())
+        .map(|(name, mut products)| {
+            products.sort_by_key(|entry| (!entry.product.featured, entry.product.sort_weight));
+            ProductCategory { name, products }
+        })
+        .collect()
+}
+
 #[get("/<product>")]
 pub async fn get_product<'a>(
     host: &'a Host<'a>,
     config: &'a State<Config>,
     product: &'a str,
 ) -> Response<GetProductResponder<'a>> {
-    let storage_config = get_storage_config(config).await?;
-    let mut products = storage_config.products;
-    let pre_release_patterns = storage_config.pre_release_patterns;
-    if let Some(product_data) = products.swap_remove(product) {
-        if is_release_info(config, host) {
+    let mut storage_config = get_storage_config(config).await?;
+    let Some((product_key, _)) = storage_config.find_product(product) else {
+        return Err(Status::NotFound);
+    };
+    let product_key = product_key.to_string();
+    let release_info = is_release_info(config, host);
+    // Release info is answered for aliases too, since update checks may not follow redirects.
+    if product_key != product && !release_info {
+        return Ok(GetProductResponder::Redirect(Redirect::permanent(format!(
+            "/{}",
+            product_key
+        ))));
+    }
+    if let Some(product_data) = storage_config.products.swap_remove(&product_key) {
+        let pre_release_patterns =
+            product_pre_release_patterns(&product_data, &storage_config.pre_release_patterns)
+                .into_owned();
+        if release_info {
             Ok(GetProductResponder::LatestRelease(
                 product_data
                     .versions
                     .latest(&pre_release_patterns)
-                    .unwrap()
+                    .ok_or(Status::NotFound)?
                     .name()
                     .to_string(),
             ))
@@ -73,10 +130,12 @@ pub async fn get_product<'a>(
                 theme_name: config.theme().into(),
                 home_url: config.home_url().into(),
                 default_endpoint_url: config.default_endpoint_url().into(),
-                product_key: product.into(),
+                product_key: product_key.into(),
                 has_nightly: product_data.nightly.is_some(),
                 product: product_data,
                 pre_release_patterns,
+                channels: storage_config.channels,
+                channel: None,
             })))
         }
     } else {
@@ -84,6 +143,64 @@ pub async fn get_product<'a>(
     }
 }
 
+/// Lists the releases of a channel. On the release info domain, returns the latest version of
+/// the channel instead. The `_channel` segment is reserved like `/_files`, so the route doesn't
+/// shadow the pages of releases (`get_release_en`, ranked after it). Ranked after the flatpak
+/// routes.
+#[get("/<product>/_channel/<channel>", rank = 0)]
+pub async fn get_channel<'a>(
+    host: &'a Host<'a>,
+    config: &'a State<Config>,
+    product: &'a str,
+    channel: &'a str,
+) -> Response<GetProductResponder<'a>> {
+    let mut storage_config = get_storage_config(config).await?;
+    let Some((product_key, _)) = storage_config.find_product(product) else {
+        return Err(Status::NotFound);
+    };
+    let product_key = product_key.to_string();
+    let release_info = is_release_info(config, host);
+    if product_key != product && !release_info {
+        return Ok(GetProductResponder::Redirect(Redirect::permanent(format!(
+            "/{}/_channel/{}",
+            product_key, channel
+        ))));
+    }
+    let product_data = storage_config
+        .products
+        .swap_remove(&product_key)
+        .ok_or(Status::NotFound)?;
+    let pre_release_patterns =
+        product_pre_release_patterns(&product_data, &storage_config.pre_release_patterns)
+            .into_owned();
+    let channels = Channels::new(
+        &product_data,
+        &storage_config.channels,
+        &pre_release_patterns,
+    );
+    let latest = channels
+        .latest(&product_data.versions, channel)
+        .ok_or(Status::NotFound)?
+        .name()
+        .to_string();
+    let display_name = channels.display_name(channel).to_string();
+    if release_info {
+        return Ok(GetProductResponder::LatestRelease(latest));
+    }
+    Ok(GetProductResponder::Template(Box::new(TemplateReleases {
+        self_name: config.self_name().into(),
+        theme_name: config.theme().into(),
+        home_url: config.home_url().into(),
+        default_endpoint_url: config.default_endpoint_url().into(),
+        product_key: product_key.into(),
+        has_nightly: product_data.nightly.is_some(),
+        product: product_data,
+        pre_release_patterns,
+        channels: storage_config.channels,
+        channel: Some((channel.to_string(), display_name)),
+    })))
+}
+
 #[get("/<product>/<release>/en", rank = 1)]
 pub async fn get_release_en<'a>(
     host: &'a Host<'a>,
@@ -91,13 +208,22 @@ pub async fn get_release_en<'a>(
     config: &'a State<Config>,
     product: &'a str,
     release: &'a str,
-) -> Response<TemplateRelease<'a>> {
+) -> Response<GetReleaseResponder<'a>> {
     if is_release_info(config, host) {
         Err(Status::NotFound)
     } else {
         let storage_config = get_storage_config(config).await?;
-        let products = &storage_config.products;
-        if let Some(product_data) = products.get(product) {
+        let Some((product_key, product_data)) = storage_config.find_product(product) else {
+            return Err(Status::NotFound);
+        };
+        let release_key = canonical_release(product_data, release).ok_or(Status::NotFound)?;
+        if product_key != product || release_key != release {
+            return Ok(GetReleaseResponder::Redirect(Redirect::permanent(format!(
+                "/{}/{}/en",
+                product_key, release_key
+            ))));
+        }
+        Ok(GetReleaseResponder::Release(
             do_get_release(
                 client_addr,
                 config,
@@ -107,13 +233,13 @@ pub async fn get_release_en<'a>(
                 release,
                 None,
             )
-            .await
-        } else {
-            Err(Status::NotFound)
-        }
+            .await?,
+        ))
     }
 }
 
+/// Shows a release. On the release info domain, returns the name of the version that `latest`
+/// or `latest-<channel>` refers to instead.
 #[get("/<product>/<release>")]
 pub async fn get_release<'a>(
     host: &'a Host<'a>,
@@ -124,36 +250,89 @@ pub async fn get_release<'a>(
     release: &'a str,
 ) -> Response<GetReleaseResponder<'a>> {
     if is_release_info(config, host) {
-        Err(Status::NotFound)
+        if release != LATEST && !release.starts_with(LATEST_PREFIX) {
+            return Err(Status::NotFound);
+        }
+        // Answered for aliases too, like `get_product`.
+        let storage_config = get_storage_config(config).await?;
+        let Some((_, product_data)) = storage_config.find_product(product) else {
+            return Err(Status::NotFound);
+        };
+        let pre_release_patterns =
+            product_pre_release_patterns(product_data, &storage_config.pre_release_patterns);
+        find_release(
+            &storage_config,
+            product_data,
+            &pre_release_patterns,
+            release,
+        )
+        .map(|named_version| GetReleaseResponder::LatestRelease(named_version.name().to_string()))
+        .ok_or(Status::NotFound)
     } else {
         let storage_config = get_storage_config(config).await?;
-        let products = &storage_config.products;
-        if let Some(product_data) = products.get(product) {
-            if release == "nightly" {
-                let nightly_result = do_get_nightly(config, product, product_data).await;
-                match nightly_result {
-                    Ok(v) => return Ok(GetReleaseResponder::Nightly(v)),
-                    Err(e) if e.code == 404 => {
-                        // Continue trying to resolve this as a release below
-                    }
-                    Err(e) => return Err(e),
+        let Some((product_key, product_data)) = storage_config.find_product(product) else {
+            return Err(Status::NotFound);
+        };
+        let release_key = canonical_release(product_data, release).ok_or(Status::NotFound)?;
+        if product_key != product || release_key != release {
+            return Ok(GetReleaseResponder::Redirect(Redirect::permanent(format!(
+                "/{}/{}",
+                product_key, release_key
+            ))));
+        }
+        if release == NIGHTLY {
+            let nightly_result = do_get_nightly(config, product, product_data).await;
+            match nightly_result {
+                Ok(v) => return Ok(GetReleaseResponder::Nightly(v)),
+                Err(e) if e.code == 404 => {
+                    // Continue trying to resolve this as a release below
                 }
+                Err(e) => return Err(e),
             }
-            Ok(GetReleaseResponder::Release(
-                do_get_release(
-                    client_addr,
-                    config,
-                    &storage_config,
-                    product,
-                    product_data,
-                    release,
-                    Some(accept_language),
-                )
-                .await?,
-            ))
-        } else {
-            Err(Status::NotFound)
         }
+        Ok(GetReleaseResponder::Release(
+            do_get_release(
+                client_addr,
+                config,
+                &storage_config,
+                product,
+                product_data,
+                release,
+                Some(accept_language),
+            )
+            .await?,
+        ))
+    }
+}
+
+/// Returns the canonical name of a release from a URL: `latest`, `nightly`, the name of a
+/// version (see `ReleaseMap::find`) or `latest-<channel>`.
+fn canonical_release<'a>(product_data: &'a Product, release: &'a str) -> Option<&'a str> {
+    match release {
+        LATEST | NIGHTLY => Some(release),
+        _ => product_data
+            .versions
+            .find(release)
+            .or_else(|| release.starts_with(LATEST_PREFIX).then_some(release)),
+    }
+}
+
+/// Returns the version a release refers to: `latest`, the name of a version or
+/// `latest-<channel>`.
+fn find_release<'a>(
+    storage_config: &'a ProductsConfig,
+    product_data: &'a Product,
+    pre_release_patterns: &'a [PreReleasePatternEntry],
+    release: &'a str,
+) -> Option<NamedVersion<'a>> {
+    if release == LATEST {
+        product_data.versions.latest(pre_release_patterns)
+    } else if let Some(named_version) = product_data.versions.get(release) {
+        Some(named_version)
+    } else {
+        let channel = release.strip_prefix(LATEST_PREFIX)?;
+        Channels::new(product_data, &storage_config.channels, pre_release_patterns)
+            .latest(&product_data.versions, channel)
     }
 }
 
@@ -166,19 +345,24 @@ async fn do_get_release<'a>(
     release: &'a str,
     #[allow(unused)] accept_language: Option<&AcceptLanguage>,
 ) -> Response<TemplateRelease<'a>> {
-    let latest = product_data
+    let pre_release_patterns =
+        product_pre_release_patterns(product_data, &storage_config.pre_release_patterns);
+    let latest_version = product_data
         .versions
-        .latest(&storage_config.pre_release_patterns);
+        .latest(&pre_release_patterns)
+        .map(|latest| latest.name().to_string());
 
-    let named_version: NamedVersion = if release == LATEST {
-        latest.as_ref().unwrap().clone()
-    } else if let Some(named_version) = product_data.versions.get(release) {
-        named_version
-    } else {
-        return Err(Status::NotFound);
-    };
+    let named_version = find_release(storage_config, product_data, &pre_release_patterns, release)
+        .ok_or(Status::NotFound)?;
+    let latest_version = latest_version.filter(|latest| latest != named_version.name());
 
-    let mut iter_versions = product_data.versions.map().keys();
+    // Versions that are not listed are left out, unless they are the current version.
+    let mut iter_versions = product_data
+        .versions
+        .map()
+        .iter()
+        .filter(|(name, info)| info.is_listed() || *name == named_version.name())
+        .map(|(name, _)| name);
     let mut product_version_prev = None;
     let product_version_next;
 
@@ -240,12 +424,20 @@ async fn do_get_release<'a>(
 
     let (artifacts, unsupported_artifacts) = artifacts_collect(
         product_key,
-        &product_data.settings,
+        product_data,
         &named_version,
         config.artifact_types(),
-        config.endpoints(),
-        #[cfg(feature = "s3_bucket_list")]
-        config.get_bucket_list().await,
+        config.storage(),
+        config
+            .get_mirror_listings(&format!("{}/{}/", product_key, named_version.name()))
+            .await,
+        &config
+            .get_checksums(
+                product_key,
+                named_version.name(),
+                &product_data.checksum_files,
+            )
+            .await,
     )
     .await;
 
@@ -279,12 +471,25 @@ async fn do_get_release<'a>(
         product_version: named_version.name().to_string().into(),
         product_version_prev: product_version_prev.cloned().map(Into::into),
         product_version_next: product_version_next.cloned().map(Into::into),
-        release_date: named_version.info().date.clone().into(),
+        release_date: named_version.info().date.clone(),
+        yanked_reason: named_version
+            .info()
+            .yanked
+            .as_ref()
+            .map(|yanked| yanked.reason.clone().into()),
+        support_status: named_version.info().support_status(),
+        support_until: named_version
+            .info()
+            .support
+            .as_ref()
+            .map(|support| support.until.clone())
+            .unwrap_or_default(),
+        latest_version: latest_version.map(Into::into),
         has_nightly: product_data.nightly.is_some(),
         product_icon: product_data.icon_path.clone().map(Into::into),
         description,
         extra_description,
-        pre_release: parse_pre_release(named_version.name(), &storage_config.pre_release_patterns)
+        pre_release: parse_pre_release(named_version.name(), &pre_release_patterns)
             .as_ref()
             .map(ToString::to_string)
             .map(Into::into),
@@ -294,7 +499,13 @@ async fn do_get_release<'a>(
             .endpoints()
             .get_all()
             .iter()
-            .map(|s| (s.key.as_str().into(), s.display_name.as_str().into()))
+            .map(|s| {
+                (
+                    s.key.as_str().into(),
+                    s.display_name.as_str().into(),
+                    config.storage().health(&s.key).is_available(),
+                )
+            })
             .collect(),
         auto_endpoint: auto_endpoint.clone().into(),
         translate_note_text_en,
@@ -303,7 +514,7 @@ async fn do_get_release<'a>(
 }
 
 #[get("/nightly-download/<product>/<artifacttype>")]
-pub async fn get_nightly_artifact<'r>(
+pub async fn get_nightly_artifact(
     host: &Host<'_>,
     config: &State<Config>,
     product: &str,
@@ -313,11 +524,16 @@ pub async fn get_nightly_artifact<'r>(
         Err(Status::NotFound)
     } else {
         let storage_config = get_storage_config(config).await?;
-        let products = &storage_config.products;
-        if let Some(product_data) = products.get(product) {
-            do_get_nightly_artifact(config, product, product_data, artifacttype).await
-        } else {
-            Err(Status::NotFound)
+        match storage_config.find_product(product) {
+            Some((product_key, _)) if product_key != product => {
+                Ok(NightlyArtifactResponder::Redirect(Redirect::permanent(
+                    format!("/nightly-download/{}/{}", product_key, artifacttype),
+                )))
+            }
+            Some((_, product_data)) => {
+                do_get_nightly_artifact(config, product, product_data, artifacttype).await
+            }
+            None => Err(Status::NotFound),
         }
     }
 }
@@ -352,6 +568,49 @@ pub async fn get_banner_png(config: &State<Config>) -> Response<BodyAndHeaders>
     }
 }
 
+/// Serves the files of local endpoints that don't have a URL configured
+/// (see `config::LOCAL_FILES_ROUTE`).
+#[get("/_files/<endpoint>/<path..>")]
+pub async fn get_local_file(
+    config: &State<Config>,
+    endpoint: &str,
+    path: PathBuf,
+) -> Option<NamedFile> {
+    let root = config.endpoints().get(endpoint)?.local_path.as_ref()?;
+    NamedFile::open(root.join(path)).await.ok()
+}
+
+/// Returns the health of all endpoints as plain text, one line per endpoint. Responds with
+/// 503 if no endpoint is available.
+#[get("/_health")]
+pub fn get_health(config: &State<Config>) -> (Status, String) {
+    let mut any_available = false;
+    let mut lines = Vec::with_capacity(config.endpoints().get_all().len());
+    for endpoint in config.endpoints().get_all() {
+        let health = config.storage().health(&endpoint.key);
+        any_available |= health.is_available();
+        lines.push(format!(
+            "{}: {:?}, latency: {}, last checked: {}",
+            endpoint.key,
+            health.status,
+            health
+                .latency
+                .map(|l| format!("{}ms", l.as_millis()))
+                .unwrap_or_else(|| "n/a".to_string()),
+            health
+                .last_checked
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_else(|| "never".to_string()),
+        ));
+    }
+    let status = if any_available {
+        Status::Ok
+    } else {
+        Status::ServiceUnavailable
+    };
+    (status, lines.join("\n"))
+}
+
 #[get("/favicon.ico")]
 pub fn favicon(config: &State<Config>) -> Redirect {
     Redirect::permanent(format!("/static/theme/{}/favicon.ico", config.theme()))
@@ -402,9 +661,12 @@ pub(crate) async fn get_storage_config(config: &State<Config>) -> Result<Product
     })
 }
 
+#[allow(clippy::large_enum_variant)]
 pub enum GetProductResponder<'a> {
     LatestRelease(String),
     Template(Box<TemplateReleases<'a>>),
+    /// Redirect from an alias to the canonical URL.
+    Redirect(Redirect),
 }
 
 impl<'r> Responder<'r, 'r> for GetProductResponder<'r> {
@@ -412,21 +674,27 @@ impl<'r> Responder<'r, 'r> for GetProductResponder<'r> {
         match self {
             GetProductResponder::LatestRelease(release) => release.respond_to(request),
             GetProductResponder::Template(tpl) => tpl.respond_to(request),
+            GetProductResponder::Redirect(v) => v.respond_to(request),
         }
     }
 }
 
 #[allow(clippy::large_enum_variant)]
 pub enum GetReleaseResponder<'a> {
+    LatestRelease(String),
     Release(TemplateRelease<'a>),
     Nightly(TemplateNightly<'a>),
+    /// Redirect from an alias to the canonical URL.
+    Redirect(Redirect),
 }
 
 impl<'r> Responder<'r, 'r> for GetReleaseResponder<'r> {
     fn respond_to(self, request: &'r Request<'_>) -> rocket::response::Result<'r> {
         match self {
+            GetReleaseResponder::LatestRelease(release) => release.respond_to(request),
             GetReleaseResponder::Release(v) => v.respond_to(request),
             GetReleaseResponder::Nightly(v) => v.respond_to(request),
+            GetReleaseResponder::Redirect(v) => v.respond_to(request),
         }
     }
 }
@@ -492,3 +760,105 @@ impl<'r> FromRequest<'r> for ForwardedIpAddr {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rocket::local::asynchronous::Client;
+    use rocket::route::{self, BoxFuture};
+    use rocket::{routes, Data, Route};
+
+    use super::*;
+
+    /// Responds with the name of the matched route.
+    fn route_name<'r>(request: &'r Request<'_>, _: Data<'r>) -> BoxFuture<'r> {
+        let name = request
+            .route()
+            .and_then(|route| route.name.as_deref())
+            .unwrap_or_default()
+            .to_string();
+        Box::pin(async move { route::Outcome::from(request, name) })
+    }
+
+    /// Client for the routes of the pages, with the same ranks, which respond with the name of
+    /// the matched route instead of calling the handlers.
+    async fn client() -> Client {
+        #[allow(unused_mut)]
+        let mut routes = routes![
+            get_root,
+            get_product,
+            get_channel,
+            get_release_en,
+            get_release,
+            get_nightly_artifact,
+            get_banner,
+            get_banner_png,
+            get_local_file,
+            get_health,
+            favicon,
+        ];
+        #[cfg(feature = "flatpak")]
+        {
+            use crate::r#impl::artifacttype::r#impl::flatpak::*;
+            routes.extend(routes![
+                get_flatpakref,
+                get_flatpakref_beta,
+                get_flatpakref_custom
+            ]);
+        }
+        let routes: Vec<_> = routes
+            .into_iter()
+            .map(|route| {
+                let mut named =
+                    Route::ranked(route.rank, route.method, route.uri.as_str(), route_name);
+                named.name = route.name;
+                named
+            })
+            .collect();
+        Client::untracked(rocket::build().mount("/", routes))
+            .await
+            .unwrap()
+    }
+
+    async fn matched_route(client: &Client, uri: &'static str) -> String {
+        client
+            .get(uri)
+            .dispatch()
+            .await
+            .into_string()
+            .await
+            .unwrap_or_default()
+    }
+
+    #[rocket::async_test]
+    async fn channel_route_does_not_shadow_releases() {
+        let client = client().await;
+        assert_eq!(
+            matched_route(&client, "/tool/_channel/beta").await,
+            "get_channel"
+        );
+        assert_eq!(
+            matched_route(&client, "/tool/channel/en").await,
+            "get_release_en"
+        );
+        assert_eq!(matched_route(&client, "/tool/channel").await, "get_release");
+        assert_eq!(
+            matched_route(&client, "/tool/1.0.0/en").await,
+            "get_release_en"
+        );
+        assert_eq!(matched_route(&client, "/tool").await, "get_product");
+    }
+
+    #[cfg(feature = "flatpak")]
+    #[rocket::async_test]
+    async fn flatpak_routes_take_precedence() {
+        let client = client().await;
+        assert_eq!(
+            matched_route(&client, "/flathub/tool/1.0.0").await,
+            "get_flatpakref"
+        );
+        assert_eq!(
+            matched_route(&client, "/flathub/_channel/beta").await,
+            "get_flatpakref"
+        );
+    }
+}
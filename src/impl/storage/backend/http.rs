@@ -0,0 +1,188 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use log::debug;
+use reqwest::header::{CONTENT_LENGTH, ETAG, LAST_MODIFIED};
+use reqwest::StatusCode;
+#[cfg(feature = "s3_bucket_list")]
+use s3::creds::Credentials;
+#[cfg(feature = "s3_bucket_list")]
+use s3::error::S3Error;
+#[cfg(feature = "s3_bucket_list")]
+use s3::{Bucket, Region};
+
+use crate::r#impl::config::S3Addressing;
+use crate::r#impl::storage::{StorageBackend, StorageError, StorageObject};
+
+/// Backend for endpoints that serve their files via HTTP. If the `s3_bucket_list` feature
+/// is enabled, the endpoint is listed as an S3-compatible bucket. Private buckets are accessed
+/// via the S3 API only, and downloads use presigned URLs.
+pub struct HttpStorageBackend {
+    url: String,
+    #[cfg(feature = "s3_bucket_list")]
+    bucket: Option<Box<Bucket>>,
+    /// Expiry of presigned URLs in seconds, if the bucket is private.
+    #[cfg(feature = "s3_bucket_list")]
+    presign_expiry: Option<u32>,
+}
+
+impl HttpStorageBackend {
+    pub fn new(url: &str, s3: Option<&S3Addressing>) -> Result<Self, StorageError> {
+        #[cfg(not(feature = "s3_bucket_list"))]
+        let _ = s3;
+        Ok(Self {
+            url: url.trim_end_matches('/').to_string(),
+            #[cfg(feature = "s3_bucket_list")]
+            bucket: s3.map(Self::bucket).transpose()?,
+            #[cfg(feature = "s3_bucket_list")]
+            presign_expiry: s3
+                .and_then(|s3| s3.credentials.as_ref())
+                .map(|c| c.presign_expiry),
+        })
+    }
+
+    #[cfg(feature = "s3_bucket_list")]
+    fn bucket(s3: &S3Addressing) -> Result<Box<Bucket>, StorageError> {
+        let region = Region::Custom {
+            region: s3.region.clone(),
+            endpoint: s3.endpoint.clone(),
+        };
+        let bucket = match &s3.credentials {
+            Some(credentials) => Bucket::new(
+                &s3.bucket,
+                region,
+                Credentials::new(
+                    Some(&credentials.access_key),
+                    Some(&credentials.secret_key),
+                    None,
+                    None,
+                    None,
+                )
+                .map_err(S3Error::from)?,
+            )?,
+            None => Box::new(Bucket::new_public(&s3.bucket, region)?),
+        };
+        Ok(if s3.path_style {
+            bucket.with_path_style()
+        } else {
+            bucket
+        })
+    }
+
+    /// Returns the bucket, if it is private and must be accessed via the S3 API.
+    #[cfg(feature = "s3_bucket_list")]
+    fn private_bucket(&self) -> Option<&Bucket> {
+        self.bucket
+            .as_deref()
+            .filter(|_| self.presign_expiry.is_some())
+    }
+
+    #[cfg(feature = "s3_bucket_list")]
+    async fn stat_private_object(
+        bucket: &Bucket,
+        key: &str,
+    ) -> Result<Option<StorageObject>, StorageError> {
+        debug!("Requesting metadata of {} in bucket {}", key, bucket.name);
+        match bucket.head_object(key).await {
+            Ok((head, _)) => Ok(Some(StorageObject {
+                key: key.to_string(),
+                size: head.content_length.unwrap_or_default().max(0) as u64,
+                last_modified: head
+                    .last_modified
+                    .and_then(|v| DateTime::parse_from_rfc2822(&v).ok())
+                    .map(|d| d.with_timezone(&Utc)),
+                e_tag: head.e_tag,
+            })),
+            Err(S3Error::HttpFailWithBody(404, _)) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn object_url(&self, key: &str) -> String {
+        format!("{}/{}", &self.url, key)
+    }
+}
+
+#[async_trait]
+impl StorageBackend for HttpStorageBackend {
+    async fn fetch_file(&self, path: &str) -> Result<String, StorageError> {
+        #[cfg(feature = "s3_bucket_list")]
+        if let Some(bucket) = self.private_bucket() {
+            let response = bucket.get_object(path).await?;
+            return Ok(String::from_utf8_lossy(response.as_slice()).into_owned());
+        }
+        Ok(reqwest::get(self.object_url(path))
+            .await?
+            .error_for_status()?
+            .text()
+            .await?)
+    }
+
+    #[cfg(feature = "s3_bucket_list")]
+    async fn list_objects(&self, prefix: &str) -> Result<Vec<StorageObject>, StorageError> {
+        let bucket = self
+            .bucket
+            .as_ref()
+            .ok_or(StorageError::ListingNotSupported)?;
+        debug!(
+            "Listing bucket {} at {} with prefix '{}'",
+            bucket.name,
+            bucket.region.endpoint(),
+            prefix
+        );
+        Ok(bucket
+            .list(prefix.to_string(), None)
+            .await?
+            .into_iter()
+            .flat_map(|page| page.contents)
+            .map(|object| StorageObject {
+                last_modified: DateTime::parse_from_rfc3339(&object.last_modified)
+                    .ok()
+                    .map(Into::into),
+                key: object.key,
+                size: object.size,
+                e_tag: object.e_tag,
+            })
+            .collect())
+    }
+
+    #[cfg(not(feature = "s3_bucket_list"))]
+    async fn list_objects(&self, _prefix: &str) -> Result<Vec<StorageObject>, StorageError> {
+        Err(StorageError::ListingNotSupported)
+    }
+
+    async fn stat_object(&self, key: &str) -> Result<Option<StorageObject>, StorageError> {
+        #[cfg(feature = "s3_bucket_list")]
+        if let Some(bucket) = self.private_bucket() {
+            return Self::stat_private_object(bucket, key).await;
+        }
+        debug!("Requesting metadata of {}", self.object_url(key));
+        let response = reqwest::Client::new()
+            .head(self.object_url(key))
+            .send()
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = response.error_for_status()?;
+        let headers = response.headers();
+        let header_str = |name| headers.get(name).and_then(|v| v.to_str().ok());
+        Ok(Some(StorageObject {
+            key: key.to_string(),
+            size: header_str(CONTENT_LENGTH)
+                .and_then(|v| v.parse().ok())
+                .unwrap_or_default(),
+            last_modified: header_str(LAST_MODIFIED)
+                .and_then(|v| DateTime::parse_from_rfc2822(v).ok())
+                .map(|d| d.with_timezone(&Utc)),
+            e_tag: header_str(ETAG).map(ToString::to_string),
+        }))
+    }
+
+    async fn download_url(&self, key: &str) -> Result<String, StorageError> {
+        #[cfg(feature = "s3_bucket_list")]
+        if let (Some(bucket), Some(expiry)) = (self.private_bucket(), self.presign_expiry) {
+            return Ok(bucket.presign_get(key, expiry, None).await?);
+        }
+        Ok(self.object_url(key))
+    }
+}
@@ -0,0 +1,98 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use log::debug;
+use tokio::fs::{metadata, read_dir, read_to_string};
+
+use crate::r#impl::storage::{StorageBackend, StorageError, StorageObject};
+
+/// Backend for endpoints that are a directory on the local filesystem. The files are
+/// expected to be served under `url` (either by Deposit Box itself or by another web server).
+pub struct LocalStorageBackend {
+    root: PathBuf,
+    url: String,
+}
+
+impl LocalStorageBackend {
+    pub fn new(root: &Path, url: &str) -> Result<Self, String> {
+        if !root.is_dir() {
+            return Err(format!(
+                "Local endpoint directory {} does not exist.",
+                root.display()
+            ));
+        }
+        Ok(Self {
+            root: root.to_path_buf(),
+            url: url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    fn object(key: String, metadata: &std::fs::Metadata) -> StorageObject {
+        StorageObject {
+            key,
+            size: metadata.len(),
+            last_modified: metadata.modified().ok().map(DateTime::<Utc>::from),
+            e_tag: None,
+        }
+    }
+}
+
+#[async_trait]
+impl StorageBackend for LocalStorageBackend {
+    async fn fetch_file(&self, path: &str) -> Result<String, StorageError> {
+        Ok(read_to_string(self.root.join(path)).await?)
+    }
+
+    async fn list_objects(&self, prefix: &str) -> Result<Vec<StorageObject>, StorageError> {
+        debug!(
+            "Listing directory {} with prefix '{}'",
+            self.root.display(),
+            prefix
+        );
+        // Start at the deepest directory contained in the prefix, no need to walk the rest.
+        let start_dir = match prefix.rsplit_once('/') {
+            Some((dir, _)) => dir,
+            None => "",
+        };
+        let mut out = Vec::new();
+        let mut pending = vec![start_dir.to_string()];
+        while let Some(dir) = pending.pop() {
+            let mut entries = match read_dir(self.root.join(&dir)).await {
+                Ok(entries) => entries,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err.into()),
+            };
+            while let Some(entry) = entries.next_entry().await? {
+                let name = entry.file_name().to_string_lossy().to_string();
+                let key = if dir.is_empty() {
+                    name
+                } else {
+                    format!("{}/{}", dir, name)
+                };
+                let entry_metadata = entry.metadata().await?;
+                if entry_metadata.is_dir() {
+                    pending.push(key);
+                } else if key.starts_with(prefix) {
+                    out.push(Self::object(key, &entry_metadata));
+                }
+            }
+        }
+        out.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(out)
+    }
+
+    async fn stat_object(&self, key: &str) -> Result<Option<StorageObject>, StorageError> {
+        match metadata(self.root.join(key)).await {
+            Ok(m) if m.is_file() => Ok(Some(Self::object(key.to_string(), &m))),
+            Ok(_) => Ok(None),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn download_url(&self, key: &str) -> Result<String, StorageError> {
+        Ok(format!("{}/{}", &self.url, key))
+    }
+}
@@ -0,0 +1,5 @@
+pub use http::HttpStorageBackend;
+pub use local::LocalStorageBackend;
+
+mod http;
+mod local;
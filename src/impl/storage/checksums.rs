@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+
+/// Download attribute with the SHA-256 checksum of the file, in hex.
+pub const CHECKSUM_SHA256: &str = "sha256";
+/// Download attribute with the SHA-512 checksum of the file, in hex.
+pub const CHECKSUM_SHA512: &str = "sha512";
+
+/// Checksums of a file, in lowercase hex.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Checksums {
+    pub sha256: Option<String>,
+    pub sha512: Option<String>,
+}
+
+impl Checksums {
+    /// Fills the checksums that are not set with the ones of `other`.
+    pub fn or(self, other: Option<&Checksums>) -> Self {
+        match other {
+            None => self,
+            Some(other) => Self {
+                sha256: self.sha256.or_else(|| other.sha256.clone()),
+                sha512: self.sha512.or_else(|| other.sha512.clone()),
+            },
+        }
+    }
+
+    /// Returns the checksums that are set, with the name of their algorithm.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        [
+            (CHECKSUM_SHA256, self.sha256.as_deref()),
+            (CHECKSUM_SHA512, self.sha512.as_deref()),
+        ]
+        .into_iter()
+        .filter_map(|(algorithm, value)| value.map(|value| (algorithm, value)))
+    }
+
+    fn set(&mut self, hex: &str) -> bool {
+        let slot = match hex.len() {
+            64 => &mut self.sha256,
+            128 => &mut self.sha512,
+            _ => return false,
+        };
+        *slot = Some(hex.to_ascii_lowercase());
+        true
+    }
+}
+
+/// Whether `value` is a valid checksum of the algorithm in hex.
+pub fn is_valid_checksum(algorithm: &str, value: &str) -> bool {
+    let len = match algorithm {
+        CHECKSUM_SHA256 => 64,
+        CHECKSUM_SHA512 => 128,
+        _ => return false,
+    };
+    value.len() == len && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Parses a checksum file as written by `sha256sum` or `sha512sum` (`<checksum>  <file name>`,
+/// with `*` before the file name in binary mode, or `SHA256 (<file name>) = <checksum>` with
+/// `--tag`) and returns the checksums by file name.
+/// The algorithm is told by the length of the checksum, so files can mix SHA-256 and SHA-512.
+/// Lines that can not be parsed are skipped.
+pub fn parse_checksum_file(raw: &str) -> HashMap<String, Checksums> {
+    let mut checksums: HashMap<String, Checksums> = HashMap::new();
+    for line in raw.lines() {
+        let line = line.trim();
+        let Some((hex, file_name)) = parse_tagged_line(line).or_else(|| parse_line(line)) else {
+            continue;
+        };
+        if file_name.is_empty() || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            continue;
+        }
+        let mut entry = checksums.get(file_name).cloned().unwrap_or_default();
+        if entry.set(hex) {
+            checksums.insert(file_name.to_string(), entry);
+        }
+    }
+    checksums
+}
+
+/// Returns the checksum and file name of a line in the default format.
+fn parse_line(line: &str) -> Option<(&str, &str)> {
+    let (hex, file_name) = line.split_once(char::is_whitespace)?;
+    let file_name = file_name.trim_start();
+    Some((hex, file_name.strip_prefix('*').unwrap_or(file_name)))
+}
+
+/// Returns the checksum and file name of a line in the BSD format (`--tag`), if the checksum
+/// has the length of the named algorithm.
+fn parse_tagged_line(line: &str) -> Option<(&str, &str)> {
+    let (algorithm, rest) = line.split_once(" (")?;
+    let (file_name, hex) = rest.rsplit_once(") = ")?;
+    let len = match algorithm {
+        "SHA256" => 64,
+        "SHA512" => 128,
+        _ => return None,
+    };
+    (hex.len() == len).then_some((hex, file_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHA256: &str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+    const SHA512: &str = "ee26b0dd4af7e749aa1a8ee3c10ae9923f618980772e473f8819a5d4940e0db27ac185f8a0e1d5f84f88bc887fd67b143732c304cc5fa9ad8e6f57f50028a8ff";
+
+    fn sha256(file_name: &str, checksums: &HashMap<String, Checksums>) -> Option<String> {
+        checksums.get(file_name).and_then(|c| c.sha256.clone())
+    }
+
+    #[test]
+    fn parses_text_and_binary_mode() {
+        let checksums =
+            parse_checksum_file(&format!("{SHA256}  tool-win64.exe\n{SHA256} *tool.dmg\n"));
+        assert_eq!(checksums.len(), 2);
+        assert_eq!(
+            sha256("tool-win64.exe", &checksums).as_deref(),
+            Some(SHA256)
+        );
+        assert_eq!(sha256("tool.dmg", &checksums).as_deref(), Some(SHA256));
+    }
+
+    #[test]
+    fn parses_tagged_lines() {
+        let checksums = parse_checksum_file(&format!(
+            "SHA256 (tool (portable).zip) = {SHA256}\nSHA512 (tool.exe) = {SHA512}\n"
+        ));
+        assert_eq!(
+            sha256("tool (portable).zip", &checksums).as_deref(),
+            Some(SHA256)
+        );
+        assert_eq!(checksums["tool.exe"].sha512.as_deref(), Some(SHA512));
+    }
+
+    #[test]
+    fn skips_tagged_lines_with_wrong_length() {
+        let checksums = parse_checksum_file(&format!(
+            "SHA512 (tool.exe) = {SHA256}\nMD5 (tool.dmg) = d41d8cd98f00b204e9800998ecf8427e\n"
+        ));
+        assert!(checksums.is_empty());
+    }
+
+    #[test]
+    fn mixes_algorithms_by_length() {
+        let checksums = parse_checksum_file(&format!("{SHA256}  tool.exe\n{SHA512}  tool.exe\n"));
+        assert_eq!(
+            checksums["tool.exe"],
+            Checksums {
+                sha256: Some(SHA256.to_string()),
+                sha512: Some(SHA512.to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn skips_blank_and_comment_lines() {
+        let checksums = parse_checksum_file(&format!(
+            "# Checksums of tool 1.0.0\n\n   \n{SHA256}  tool.exe\n\n"
+        ));
+        assert_eq!(checksums.len(), 1);
+        assert_eq!(sha256("tool.exe", &checksums).as_deref(), Some(SHA256));
+    }
+
+    #[test]
+    fn lowercases_uppercase_hex() {
+        let checksums = parse_checksum_file(&format!("{}  tool.exe", SHA256.to_uppercase()));
+        assert_eq!(sha256("tool.exe", &checksums).as_deref(), Some(SHA256));
+    }
+
+    #[test]
+    fn skips_malformed_lines() {
+        let checksums = parse_checksum_file(&format!(
+            "not a checksum line\n{}  short.exe\n{SHA256}\nxyz{}  nothex.exe\n{SHA256}  tool.exe\n",
+            &SHA256[..10],
+            &SHA256[3..],
+        ));
+        assert_eq!(checksums.len(), 1);
+        assert_eq!(sha256("tool.exe", &checksums).as_deref(), Some(SHA256));
+    }
+
+    #[test]
+    fn keeps_spaces_in_file_names() {
+        let checksums = parse_checksum_file(&format!("{SHA256}  tool setup.exe"));
+        assert_eq!(
+            sha256("tool setup.exe", &checksums).as_deref(),
+            Some(SHA256)
+        );
+    }
+}
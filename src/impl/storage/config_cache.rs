@@ -0,0 +1,177 @@
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use tokio::fs::{create_dir_all, read_to_string, remove_file, rename, File};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+use crate::r#impl::storage::ProductsConfig;
+
+/// How long a loaded configuration is used before it is refreshed.
+const CONFIG_MAX_AGE: Duration = Duration::from_secs(900);
+/// How long to wait before trying again after a refresh failed.
+const CONFIG_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Cache for the product configuration, that keeps the last successfully loaded version.
+#[derive(Default)]
+pub(super) struct ConfigCache {
+    current: RwLock<Option<CachedConfig>>,
+    /// Held while the configuration is being loaded.
+    pub refresh_lock: Mutex<()>,
+}
+
+struct CachedConfig {
+    config: ProductsConfig,
+    refresh_at: Instant,
+}
+
+impl ConfigCache {
+    /// Returns the cached configuration and whether it should be refreshed.
+    pub fn get(&self) -> Option<(ProductsConfig, bool)> {
+        self.current
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|c| (c.config.clone(), Instant::now() >= c.refresh_at))
+    }
+
+    /// Stores a freshly loaded configuration.
+    pub fn set(&self, config: ProductsConfig) {
+        *self.current.write().unwrap() = Some(CachedConfig {
+            config,
+            refresh_at: Instant::now() + CONFIG_MAX_AGE,
+        });
+    }
+
+    /// Stores a configuration that was not loaded from the endpoints. It will be refreshed soon.
+    pub fn set_failed(&self, config: ProductsConfig) {
+        *self.current.write().unwrap() = Some(CachedConfig {
+            config,
+            refresh_at: Instant::now() + CONFIG_RETRY_AFTER,
+        });
+    }
+
+    /// Keeps the current configuration after a failed refresh and schedules the next attempt.
+    pub fn mark_refresh_failed(&self) {
+        if let Some(current) = self.current.write().unwrap().as_mut() {
+            current.refresh_at = Instant::now() + CONFIG_RETRY_AFTER;
+        }
+    }
+}
+
+/// Returns the directory the configuration files are persisted in.
+fn persisted_files_dir() -> io::Result<PathBuf> {
+    let dirs = xdg::BaseDirectories::with_prefix("deposit-box").map_err(io::Error::other)?;
+    Ok(dirs.get_cache_home().join("config"))
+}
+
+/// Returns the path a configuration file is persisted at. The name is a path relative to the
+/// root of the endpoints (e.g. of an included file), names that are absolute or contain `..`
+/// are rejected.
+fn persisted_file_path(name: &str) -> io::Result<PathBuf> {
+    Ok(persisted_files_dir()?.join(relative_path(name)?))
+}
+
+fn relative_path(name: &str) -> io::Result<&Path> {
+    let path = Path::new(name);
+    if name.is_empty()
+        || !path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("'{}' is not a relative path", name),
+        ));
+    }
+    Ok(path)
+}
+
+/// Stores a copy of a configuration file loaded from the endpoints on disk.
+pub(super) async fn persist_file(name: &str, content: &str) -> io::Result<()> {
+    write_atomically(&persisted_file_path(name)?, content).await
+}
+
+/// Writes a file by writing a temporary file next to it and renaming it, so the file is never
+/// left partially written.
+async fn write_atomically(path: &Path, content: &str) -> io::Result<()> {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+    let (Some(parent), Some(file_name)) = (path.parent(), path.file_name()) else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("'{}' is not a file path", path.display()),
+        ));
+    };
+    create_dir_all(parent).await?;
+    let temp_path = parent.join(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        NEXT_ID.fetch_add(1, Ordering::Relaxed)
+    ));
+    let result = async {
+        let mut file = File::create(&temp_path).await?;
+        file.write_all(content.as_bytes()).await?;
+        file.sync_all().await?;
+        rename(&temp_path, path).await
+    }
+    .await;
+    if result.is_err() {
+        let _ = remove_file(&temp_path).await;
+    }
+    result
+}
+
+/// Returns the last copy of a configuration file stored with `persist_file`, if any.
+pub(super) async fn load_persisted_file(name: &str) -> Option<String> {
+    read_to_string(persisted_file_path(name).ok()?).await.ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_relative_paths() {
+        assert_eq!(
+            relative_path("products.yml").unwrap(),
+            Path::new("products.yml")
+        );
+        assert_eq!(
+            relative_path("products/tool.yml").unwrap(),
+            Path::new("products/tool.yml")
+        );
+    }
+
+    #[test]
+    fn rejects_paths_outside_of_the_directory() {
+        for name in [
+            "",
+            "../x",
+            "../../x",
+            "products/../../x",
+            "/etc/passwd",
+            "./products.yml",
+        ] {
+            assert!(relative_path(name).is_err(), "{}", name);
+        }
+    }
+
+    #[rocket::async_test]
+    async fn writes_atomically() {
+        let dir = std::env::temp_dir().join(format!("deposit-box-test-{}", std::process::id()));
+        let path = dir.join("products/tool.yml");
+        write_atomically(&path, "name: Old").await.unwrap();
+        write_atomically(&path, "name: New").await.unwrap();
+        assert_eq!(read_to_string(&path).await.unwrap(), "name: New");
+        let mut entries = tokio::fs::read_dir(dir.join("products")).await.unwrap();
+        let mut names = Vec::new();
+        while let Some(entry) = entries.next_entry().await.unwrap() {
+            names.push(entry.file_name());
+        }
+        assert_eq!(names, ["tool.yml"]);
+        tokio::fs::remove_dir_all(dir).await.unwrap();
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use indexmap::IndexMap;
+use log::warn;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::r#impl::artifacttype::ArtifactKey;
+use crate::r#impl::release_map::ReleaseMap;
+use crate::r#impl::storage::{DownloadSpec, MirrorListings, VersionInfo};
+
+/// Maps the names of files found in the bucket to an artifact type, for products whose versions
+/// are discovered from the bucket layout (`product/version/file`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct DiscoveryPattern {
+    /// Must match the whole file name.
+    #[serde(with = "serde_regex")]
+    pub pattern: Regex,
+    pub artifact: ArtifactKey,
+}
+
+impl DiscoveryPattern {
+    fn matches(&self, file_name: &str) -> bool {
+        self.pattern
+            .find(file_name)
+            .is_some_and(|m| m.start() == 0 && m.end() == file_name.len())
+    }
+}
+
+impl PartialEq for DiscoveryPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern.as_str() == other.pattern.as_str() && self.artifact == other.artifact
+    }
+}
+
+impl Eq for DiscoveryPattern {}
+
+/// Builds the versions of a product from the files in the bucket listings. Every directory
+/// `product/version/` with files matching one of the patterns is a version, with the files
+/// matching a pattern as the downloads of the pattern's artifact type. The date of a version is
+/// the time its newest file was modified.
+///
+/// The configured versions take precedence: Their description, changelog and date (if set)
+/// replace the discovered ones and they can have aliases, a channel and a support status or be
+/// yanked, hidden or scheduled for publishing. Their downloads are added to the discovered
+/// downloads of the same artifact type, downloads with the URL of a discovered download replace
+/// it (to give it a label or checksums).
+/// Configured versions that were not discovered are kept as they are, they need a date.
+pub(super) fn discover_versions(
+    product_key: &str,
+    patterns: &[DiscoveryPattern],
+    listings: &MirrorListings,
+    configured: ReleaseMap,
+) -> ReleaseMap {
+    let prefix = format!("{}/", product_key);
+    let mut versions: IndexMap<String, VersionInfo> = IndexMap::new();
+    let mut modified: HashMap<String, DateTime<Utc>> = HashMap::new();
+    for key in listings.keys() {
+        let Some((version, file_name)) = key
+            .strip_prefix(&prefix)
+            .and_then(|path| path.split_once('/'))
+            .filter(|(_, file_name)| !file_name.contains('/'))
+        else {
+            continue;
+        };
+        let Some(pattern) = patterns.iter().find(|p| p.matches(file_name)) else {
+            continue;
+        };
+        versions
+            .entry(version.to_string())
+            .or_default()
+            .downloads
+            .entry(pattern.artifact.clone())
+            .or_default()
+            .push(DownloadSpec::Url(file_name.to_string()));
+        if let Some(last_modified) = listings.get(key).and_then(|o| o.last_modified) {
+            let newest = modified.entry(version.to_string()).or_insert(last_modified);
+            *newest = last_modified.max(*newest);
+        }
+    }
+    for (version, info) in versions.iter_mut() {
+        if let Some(modified) = modified.get(version) {
+            info.date = (*modified).into();
+        }
+    }
+
+    for (version, info) in configured {
+        match versions.get_mut(&version) {
+            Some(discovered) => {
+                if !info.date.is_missing() {
+                    discovered.date = info.date;
+                }
+                if info.description.is_some() {
+                    discovered.description = info.description;
+                }
+                if info.changelog.is_some() {
+                    discovered.changelog = info.changelog;
+                }
+                if info.changelog_section.is_some() {
+                    discovered.changelog_section = info.changelog_section;
+                }
+                for (key, downloads) in info.downloads {
+                    let discovered_downloads = discovered.downloads.entry(key).or_default();
+                    for download in downloads {
+                        match discovered_downloads
+                            .iter_mut()
+                            .find(|discovered| discovered.url() == download.url())
+                        {
+                            Some(discovered) => *discovered = download,
+                            None => discovered_downloads.push(download),
+                        }
+                    }
+                }
+                discovered.aliases = info.aliases;
+                discovered.channel = info.channel;
+                discovered.yanked = info.yanked;
+                discovered.support = info.support;
+                discovered.hidden = info.hidden;
+                discovered.publish_at = info.publish_at;
+            }
+            None => {
+                if info.date.is_missing() {
+                    warn!(
+                        "Version {} of {} was not discovered and has no date.",
+                        version, product_key
+                    );
+                }
+                versions.insert(version, info);
+            }
+        }
+    }
+    // Oldest first, like in products.yml.
+    versions.sort_by(|_, a, _, b| a.date.date_time().cmp(&b.date.date_time()));
+    versions.into_iter().collect()
+}
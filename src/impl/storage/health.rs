@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use tokio::time::{interval, timeout, MissedTickBehavior};
+
+use crate::r#impl::storage::{StorageBackend, PRODUCTS_YML};
+
+/// Maximum time an endpoint may take to answer a health check before it's considered down.
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HealthStatus {
+    /// The endpoint was not checked yet.
+    #[default]
+    Unknown,
+    Up,
+    Down,
+}
+
+/// Result of the last health check of an endpoint.
+#[derive(Debug, Clone, Default)]
+pub struct EndpointHealth {
+    pub status: HealthStatus,
+    pub latency: Option<Duration>,
+    pub last_checked: Option<DateTime<Utc>>,
+}
+
+impl EndpointHealth {
+    /// Whether the endpoint should be used. Endpoints not checked yet are assumed to be up.
+    pub fn is_available(&self) -> bool {
+        self.status != HealthStatus::Down
+    }
+}
+
+/// Health of all endpoints, by endpoint key.
+#[derive(Default)]
+pub(super) struct HealthMap(RwLock<HashMap<String, EndpointHealth>>);
+
+impl HealthMap {
+    pub fn get(&self, endpoint_key: &str) -> EndpointHealth {
+        self.0
+            .read()
+            .unwrap()
+            .get(endpoint_key)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn set(&self, endpoint_key: &str, health: EndpointHealth) {
+        let previous = self
+            .0
+            .write()
+            .unwrap()
+            .insert(endpoint_key.to_string(), health.clone());
+        let previous_status = previous.map(|p| p.status).unwrap_or_default();
+        match (previous_status, health.status) {
+            (HealthStatus::Down, HealthStatus::Up) => {
+                info!("Endpoint {} is available again.", endpoint_key)
+            }
+            (HealthStatus::Unknown | HealthStatus::Up, HealthStatus::Down) => warn!(
+                "Endpoint {} is unavailable and will not be used until it recovers.",
+                endpoint_key
+            ),
+            _ => {}
+        }
+    }
+}
+
+async fn check_endpoint(backend: &dyn StorageBackend) -> EndpointHealth {
+    let start = Instant::now();
+    let result = timeout(HEALTH_CHECK_TIMEOUT, backend.stat_object(PRODUCTS_YML)).await;
+    let latency = start.elapsed();
+    let status = match result {
+        Ok(Ok(Some(_))) => HealthStatus::Up,
+        Ok(Ok(None)) => {
+            debug!("Health check failed: {} not found.", PRODUCTS_YML);
+            HealthStatus::Down
+        }
+        Ok(Err(err)) => {
+            debug!("Health check failed: {}", err);
+            HealthStatus::Down
+        }
+        Err(_) => {
+            debug!("Health check failed: timed out.");
+            HealthStatus::Down
+        }
+    };
+    EndpointHealth {
+        status,
+        latency: (status == HealthStatus::Up).then_some(latency),
+        last_checked: Some(Utc::now()),
+    }
+}
+
+pub(super) async fn run_health_checker(
+    backends: Vec<(String, Arc<dyn StorageBackend>)>,
+    health: Arc<HealthMap>,
+    check_interval: Duration,
+) {
+    let mut ticker = interval(check_interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        for (key, backend) in &backends {
+            let result = check_endpoint(backend.as_ref()).await;
+            debug!("Health of endpoint {}: {:?}", key, result);
+            health.set(key, result);
+        }
+    }
+}
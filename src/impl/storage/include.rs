@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use indexmap::IndexMap;
+use serde::de::{self, DeserializeSeed, IntoDeserializer, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+
+use crate::r#impl::storage::{
+    Banner, ChannelEntry, PreReleasePatternEntry, Product, ProductsConfig,
+};
+use crate::r#impl::version_scheme::VersionScheme;
+
+/// Key of products that are loaded from another file.
+const INCLUDE_KEY: &str = "include";
+
+/// The product configuration as written in `products.yml`, before included files are loaded.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RawProductsConfig {
+    pub products: IndexMap<String, ProductSource>,
+    #[serde(default)]
+    pub banner: Option<Banner>,
+    #[serde(default)]
+    pub pre_release_patterns: Vec<PreReleasePatternEntry>,
+    /// Release channels of all products.
+    #[serde(default)]
+    pub channels: Vec<ChannelEntry>,
+    /// The version scheme of products that don't set their own.
+    #[serde(default)]
+    pub version_scheme: VersionScheme,
+}
+
+/// A product in `products.yml`: Either defined in place, or included from another file with
+/// `include: path/to/product.yml`. The path is relative to the root of the endpoint and the file
+/// contains the product definition only (see `IncludedProduct`).
+#[derive(Debug, Clone)]
+pub enum ProductSource {
+    Inline(Box<Product>),
+    Include(String),
+}
+
+/// The product in an included file. Included files can not include other files.
+#[derive(Debug, Clone)]
+pub struct IncludedProduct(pub Product);
+
+impl RawProductsConfig {
+    /// Returns the keys of all included products with the paths of their files.
+    pub fn includes(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.products
+            .iter()
+            .filter_map(|(key, source)| match source {
+                ProductSource::Include(path) => Some((key.as_str(), path.as_str())),
+                ProductSource::Inline(_) => None,
+            })
+    }
+
+    /// Builds the product configuration with the products of the included files, by path.
+    /// Included products whose file is not given are left out. The versions of all products are
+    /// sorted by their version scheme.
+    pub fn into_config(self, included: &HashMap<String, Product>) -> ProductsConfig {
+        let includes = self
+            .includes()
+            .map(|(key, path)| (key.to_string(), path.to_string()))
+            .collect();
+        let mut config = ProductsConfig {
+            products: self
+                .products
+                .into_iter()
+                .filter_map(|(key, source)| match source {
+                    ProductSource::Inline(product) => Some((key, *product)),
+                    ProductSource::Include(path) => included.get(&path).cloned().map(|p| (key, p)),
+                })
+                .collect(),
+            banner: self.banner,
+            pre_release_patterns: self.pre_release_patterns,
+            channels: self.channels,
+            version_scheme: self.version_scheme,
+            includes,
+        };
+        for product in config.products.values_mut() {
+            product
+                .versions
+                .sort(product.version_scheme.unwrap_or(self.version_scheme));
+        }
+        config
+    }
+}
+
+impl<'de> Deserialize<'de> for ProductSource {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(ProductSourceVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for IncludedProduct {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match ProductSource::deserialize(deserializer)? {
+            ProductSource::Inline(product) => Ok(IncludedProduct(*product)),
+            ProductSource::Include(path) => Err(de::Error::custom(format!(
+                "included files can not include other files, found `{}: {}`",
+                INCLUDE_KEY, path
+            ))),
+        }
+    }
+}
+
+struct ProductSourceVisitor;
+
+impl<'de> Visitor<'de> for ProductSourceVisitor {
+    type Value = ProductSource;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a product or a mapping with only an include key")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let first_key = map.next_key::<String>()?;
+        if first_key.as_deref() == Some(INCLUDE_KEY) {
+            let path = map.next_value()?;
+            return match map.next_key::<String>()? {
+                None => Ok(ProductSource::Include(path)),
+                Some(key) => Err(de::Error::custom(format!(
+                    "included products can not have other keys, found `{}`",
+                    key
+                ))),
+            };
+        }
+        // Let the product be deserialized as usual, with the key we already read.
+        <Product as Deserialize>::deserialize(de::value::MapAccessDeserializer::new(
+            ProductMapAccess {
+                peeked_key: first_key,
+                map,
+            },
+        ))
+        .map(|product| ProductSource::Inline(Box::new(product)))
+    }
+}
+
+/// Map access for products defined in place, that starts with a key that was already read and
+/// rejects include keys after the first key.
+struct ProductMapAccess<A> {
+    peeked_key: Option<String>,
+    map: A,
+}
+
+impl<'de, A> MapAccess<'de> for ProductMapAccess<A>
+where
+    A: MapAccess<'de>,
+{
+    type Error = A::Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        let key = match self.peeked_key.take() {
+            Some(key) => Some(key),
+            None => self.map.next_key::<String>()?,
+        };
+        match key {
+            Some(key) if key == INCLUDE_KEY => Err(de::Error::custom(
+                "included products can not have other keys",
+            )),
+            Some(key) => seed.deserialize(key.into_deserializer()).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        self.map.next_value_seed(seed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(yaml: &str) -> Result<RawProductsConfig, serde_yaml::Error> {
+        serde_yaml::from_str(yaml)
+    }
+
+    fn product(name: &str) -> Product {
+        serde_yaml::from_str(&format!("name: {}\nversions: {{}}", name)).unwrap()
+    }
+
+    #[test]
+    fn include_on_its_own() {
+        let config = parse("products:\n  tool:\n    include: products/tool.yml\n").unwrap();
+        assert!(matches!(
+            &config.products["tool"],
+            ProductSource::Include(path) if path == "products/tool.yml"
+        ));
+        assert_eq!(
+            config.includes().collect::<Vec<_>>(),
+            [("tool", "products/tool.yml")]
+        );
+    }
+
+    #[test]
+    fn inline_products_are_not_included() {
+        let config = parse("products:\n  tool:\n    name: Tool\n    versions: {}\n").unwrap();
+        assert!(matches!(
+            &config.products["tool"],
+            ProductSource::Inline(product) if product.name == "Tool"
+        ));
+        assert_eq!(config.includes().count(), 0);
+    }
+
+    #[test]
+    fn include_with_other_keys_fails() {
+        let err = parse("products:\n  tool:\n    include: tool.yml\n    name: Tool\n").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("included products can not have other keys, found `name`"),
+            "{}",
+            err
+        );
+        let err = parse("products:\n  tool:\n    name: Tool\n    include: tool.yml\n").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("included products can not have other keys"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn into_config_uses_included_products() {
+        let config = parse(
+            "products:\n  a:\n    include: a.yml\n  b:\n    name: B\n    versions: {}\n  c:\n    include: a.yml\n",
+        )
+        .unwrap();
+        let included = HashMap::from([("a.yml".to_string(), product("A"))]);
+        let config = config.into_config(&included);
+        assert_eq!(config.products.keys().collect::<Vec<_>>(), ["a", "b", "c"]);
+        assert_eq!(config.products["a"].name, "A");
+        assert_eq!(config.products["c"].name, "A");
+    }
+
+    #[test]
+    fn into_config_leaves_out_missing_files() {
+        let config = parse("products:\n  a:\n    include: a.yml\n  b:\n    include: b.yml\n")
+            .unwrap()
+            .into_config(&HashMap::from([("b.yml".to_string(), product("B"))]));
+        assert_eq!(config.products.keys().collect::<Vec<_>>(), ["b"]);
+    }
+
+    #[test]
+    fn included_files_contain_a_product() {
+        let IncludedProduct(product) = serde_yaml::from_str("name: Tool\nversions: {}\n").unwrap();
+        assert_eq!(product.name, "Tool");
+    }
+
+    #[test]
+    fn nested_includes_fail() {
+        let err = serde_yaml::from_str::<IncludedProduct>("include: other.yml\n").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("included files can not include other files, found `include: other.yml`"),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use indexmap::IndexMap;
+use serde::Serialize;
+
+use crate::r#impl::storage::StorageObject;
+
+/// Index of the objects of a bucket listing, by object key.
+#[derive(Debug, Clone, Default)]
+pub struct BucketIndex(HashMap<String, StorageObject>);
+
+impl BucketIndex {
+    /// Returns the metadata of the object with the given key, if it was listed.
+    pub fn get(&self, key: &str) -> Option<&StorageObject> {
+        self.0.get(key)
+    }
+
+    fn keys(&self) -> impl Iterator<Item = &String> {
+        self.0.keys()
+    }
+}
+
+impl FromIterator<StorageObject> for BucketIndex {
+    fn from_iter<T: IntoIterator<Item = StorageObject>>(iter: T) -> Self {
+        Self(iter.into_iter().map(|o| (o.key.clone(), o)).collect())
+    }
+}
+
+/// Whether an object exists on a single endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectPresence<'a> {
+    Present(&'a StorageObject),
+    Absent,
+    /// The endpoint could not be listed.
+    Unknown,
+}
+
+/// The bucket listings of all endpoints that could be listed, by endpoint key, in the order
+/// of the endpoints.
+#[derive(Debug, Clone, Default)]
+pub struct MirrorListings(IndexMap<String, BucketIndex>);
+
+impl MirrorListings {
+    pub(super) fn new(listings: IndexMap<String, BucketIndex>) -> Self {
+        Self(listings)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the metadata of the object on the first endpoint that has it.
+    pub fn get(&self, key: &str) -> Option<&StorageObject> {
+        self.0.values().find_map(|index| index.get(key))
+    }
+
+    /// Returns whether the object exists on the given endpoint.
+    pub fn presence(&self, endpoint_key: &str, key: &str) -> ObjectPresence<'_> {
+        match self.0.get(endpoint_key) {
+            Some(index) => match index.get(key) {
+                Some(object) => ObjectPresence::Present(object),
+                None => ObjectPresence::Absent,
+            },
+            None => ObjectPresence::Unknown,
+        }
+    }
+
+    /// Returns the metadata of the object on every endpoint that has it, by endpoint key.
+    pub fn per_endpoint<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> impl Iterator<Item = (&'a str, &'a StorageObject)> {
+        self.0
+            .iter()
+            .filter_map(move |(endpoint, index)| index.get(key).map(|o| (endpoint.as_str(), o)))
+    }
+
+    /// Returns the keys of all objects that were listed on any endpoint.
+    pub fn keys(&self) -> BTreeSet<&String> {
+        self.0.values().flat_map(BucketIndex::keys).collect()
+    }
+
+    /// Compares the listings of all endpoints with each other.
+    pub fn report(&self, unlisted: BTreeMap<String, String>) -> MirrorReport {
+        let mut mismatches = Vec::new();
+        for key in self.keys() {
+            let mut missing_on = Vec::new();
+            let mut sizes = BTreeMap::new();
+            let mut e_tags = BTreeMap::new();
+            for (endpoint, index) in &self.0 {
+                match index.get(key) {
+                    Some(object) => {
+                        sizes.insert(endpoint.clone(), object.size);
+                        if let Some(e_tag) = &object.e_tag {
+                            e_tags.insert(endpoint.clone(), e_tag.trim_matches('"').to_string());
+                        }
+                    }
+                    None => missing_on.push(endpoint.clone()),
+                }
+            }
+            let sizes_differ = sizes.values().collect::<BTreeSet<_>>().len() > 1;
+            let e_tags_differ = e_tags.values().collect::<BTreeSet<_>>().len() > 1;
+            if !missing_on.is_empty() || sizes_differ || e_tags_differ {
+                mismatches.push(MirrorMismatch {
+                    key: key.clone(),
+                    missing_on,
+                    sizes: if sizes_differ { sizes } else { BTreeMap::new() },
+                    e_tags: if e_tags_differ {
+                        e_tags
+                    } else {
+                        BTreeMap::new()
+                    },
+                });
+            }
+        }
+        MirrorReport {
+            listed_endpoints: self.0.keys().cloned().collect(),
+            unlisted_endpoints: unlisted,
+            mismatches,
+        }
+    }
+}
+
+/// Differences between the bucket listings of the endpoints.
+#[derive(Debug, Serialize)]
+pub struct MirrorReport {
+    /// Endpoints that could be listed and were compared.
+    pub listed_endpoints: Vec<String>,
+    /// Endpoints that could not be listed, with the reason.
+    pub unlisted_endpoints: BTreeMap<String, String>,
+    pub mismatches: Vec<MirrorMismatch>,
+}
+
+/// An object that is missing on some endpoints or differs between them.
+#[derive(Debug, Serialize)]
+pub struct MirrorMismatch {
+    pub key: String,
+    pub missing_on: Vec<String>,
+    /// Sizes by endpoint, only set if they differ.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub sizes: BTreeMap<String, u64>,
+    /// ETags by endpoint, only set if they differ.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub e_tags: BTreeMap<String, String>,
+}
@@ -0,0 +1,1181 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::r#impl::artifacttype::ArtifactKey;
+use crate::r#impl::config::{Endpoint, Endpoints};
+use crate::r#impl::nightly::NightlyConfig;
+use crate::r#impl::release_date::ReleaseDate;
+use crate::r#impl::release_map::ReleaseMap;
+use crate::r#impl::version_scheme::VersionScheme;
+use async_trait::async_trait;
+use cached::proc_macro::cached;
+use cached::stores::TimedCache;
+use cached::Cached;
+use chrono::{DateTime, Utc};
+use indexmap::IndexMap;
+use itertools::Itertools;
+use log::{debug, error, warn};
+use regex::Regex;
+#[cfg(feature = "s3_bucket_list")]
+use s3::error::S3Error;
+use serde::de::{self, DeserializeSeed, IntoDeserializer, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use serde_yaml::Value;
+use thiserror::Error;
+
+pub use crate::r#impl::storage::backend::{HttpStorageBackend, LocalStorageBackend};
+use crate::r#impl::storage::checksums::parse_checksum_file;
+pub use crate::r#impl::storage::checksums::{
+    is_valid_checksum, Checksums, CHECKSUM_SHA256, CHECKSUM_SHA512,
+};
+use crate::r#impl::storage::config_cache::{load_persisted_file, persist_file, ConfigCache};
+use crate::r#impl::storage::discovery::discover_versions;
+pub use crate::r#impl::storage::discovery::DiscoveryPattern;
+use crate::r#impl::storage::health::{run_health_checker, HealthMap};
+pub use crate::r#impl::storage::health::{EndpointHealth, HealthStatus};
+pub use crate::r#impl::storage::include::{IncludedProduct, ProductSource, RawProductsConfig};
+pub use crate::r#impl::storage::listing::{
+    BucketIndex, MirrorListings, MirrorMismatch, MirrorReport, ObjectPresence,
+};
+
+pub mod backend;
+mod checksums;
+mod config_cache;
+mod discovery;
+mod health;
+mod include;
+mod listing;
+
+pub const PRODUCTS_YML: &str = "products.yml";
+
+/// A backend that provides access to the files of a single endpoint.
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    /// Returns the content of a text file. The path is relative to the root of the endpoint.
+    async fn fetch_file(&self, path: &str) -> Result<String, StorageError>;
+
+    /// Returns the unparsed product configuration (`products.yml`) of the endpoint.
+    async fn fetch_config(&self) -> Result<String, StorageError> {
+        self.fetch_file(PRODUCTS_YML).await
+    }
+
+    /// Lists all objects on the endpoint with keys starting with `prefix`.
+    async fn list_objects(&self, prefix: &str) -> Result<Vec<StorageObject>, StorageError>;
+
+    /// Returns the metadata of a single object, or None if it does not exist.
+    async fn stat_object(&self, key: &str) -> Result<Option<StorageObject>, StorageError>;
+
+    /// Returns the URL users can download the object from. For private endpoints, the URL is
+    /// only valid for a limited time.
+    async fn download_url(&self, key: &str) -> Result<String, StorageError>;
+}
+
+/// Metadata of a single object (file) on an endpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageObject {
+    pub key: String,
+    pub size: u64,
+    pub last_modified: Option<DateTime<Utc>>,
+    pub e_tag: Option<String>,
+}
+
+/// Struct to interact with all endpoints. Cloning is cheap, clones share their state.
+#[derive(Clone)]
+pub struct Storage {
+    endpoints: Arc<Endpoints>,
+    backends: Arc<IndexMap<String, Arc<dyn StorageBackend>>>,
+    health: Arc<HealthMap>,
+    config_cache: Arc<ConfigCache>,
+    bucket_list_error_logged: Arc<Mutex<RefCell<bool>>>,
+}
+
+impl Storage {
+    pub fn new(endpoints: Endpoints) -> Result<Self, String> {
+        let mut backends: IndexMap<String, Arc<dyn StorageBackend>> = IndexMap::new();
+        for endpoint in endpoints.get_all() {
+            let backend: Arc<dyn StorageBackend> = match &endpoint.local_path {
+                Some(path) => Arc::new(LocalStorageBackend::new(path, &endpoint.url)?),
+                None => Arc::new(
+                    HttpStorageBackend::new(&endpoint.url, endpoint.s3.as_ref())
+                        .map_err(|e| format!("Endpoint {}: {}", endpoint.key, e))?,
+                ),
+            };
+            backends.insert(endpoint.key.clone(), backend);
+        }
+        Ok(Self {
+            endpoints: Arc::new(endpoints),
+            backends: Arc::new(backends),
+            health: Default::default(),
+            config_cache: Default::default(),
+            bucket_list_error_logged: Arc::new(Mutex::new(RefCell::new(false))),
+        })
+    }
+
+    pub fn endpoints(&self) -> &Endpoints {
+        &self.endpoints
+    }
+
+    /// Returns the backend of the endpoint with the given key.
+    pub fn backend(&self, endpoint_key: &str) -> Option<&dyn StorageBackend> {
+        self.backends.get(endpoint_key).map(AsRef::as_ref)
+    }
+
+    /// Returns the last known health of the endpoint with the given key.
+    pub fn health(&self, endpoint_key: &str) -> EndpointHealth {
+        self.health.get(endpoint_key)
+    }
+
+    /// Returns all endpoints that are not known to be down. If all endpoints are down,
+    /// all endpoints are returned, since the health information may be outdated.
+    pub fn available_endpoints(&self) -> Vec<&Endpoint> {
+        let available = self
+            .endpoints
+            .get_all()
+            .iter()
+            .filter(|e| self.health.get(&e.key).is_available())
+            .collect::<Vec<_>>();
+        if available.is_empty() {
+            self.endpoints.get_all().iter().collect()
+        } else {
+            available
+        }
+    }
+
+    /// Starts periodically checking the health of all endpoints in the background.
+    /// Must be called from within a Tokio runtime.
+    pub fn spawn_health_checker(&self, interval: Duration) {
+        tokio::spawn(run_health_checker(
+            self.backends
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+            self.health.clone(),
+            interval,
+        ));
+    }
+
+    /// Returns the product configuration, or an error on error. The result may be cached.
+    /// Products and versions that are not published yet are left out.
+    ///
+    /// Once loaded, the configuration is always returned from the cache. If the cached
+    /// configuration is outdated, it is refreshed in the background. If the configuration can not
+    /// be loaded from any endpoint, the last successfully loaded configuration is used, which
+    /// is also persisted to disk to survive restarts.
+    pub async fn get_config(&self) -> Result<ProductsConfig, StorageError> {
+        let mut config = self.get_cached_config().await?;
+        config.retain_published(Utc::now());
+        Ok(config)
+    }
+
+    /// Like `get_config`, but including the products and versions that are not published yet.
+    pub async fn get_cached_config(&self) -> Result<ProductsConfig, StorageError> {
+        if let Some((config, stale)) = self.config_cache.get() {
+            if stale {
+                self.spawn_config_refresh();
+            }
+            return Ok(config);
+        }
+
+        let _guard = self.config_cache.refresh_lock.lock().await;
+        // Another request may have finished loading while waiting for the lock.
+        if let Some((config, _)) = self.config_cache.get() {
+            return Ok(config);
+        }
+        match self.refresh_config(Some(&[])).await {
+            Ok(config) => Ok(config),
+            Err(err) => match load_persisted_config().await {
+                Some(mut config) => {
+                    self.discover_versions(&mut config).await;
+                    warn!(
+                        "Failed to load products.yml, using last known good version from disk: {}",
+                        err
+                    );
+                    self.config_cache.set_failed(config.clone());
+                    Ok(config)
+                }
+                None => Err(err),
+            },
+        }
+    }
+
+    /// Drops cached included files, checksum files and bucket listings, either of the given
+    /// products or of all products, and immediately loads the product configuration from the
+    /// endpoints again.
+    /// Listings that were dropped are loaded again right away. Returns the new product
+    /// configuration.
+    pub async fn refresh(
+        &self,
+        products: Option<&[String]>,
+    ) -> Result<ProductsConfig, StorageError> {
+        // Listings are dropped first, since they are needed to discover versions.
+        let dropped_prefixes = {
+            let mut cache = _IMPL_GET_BUCKET_INDEX.lock().await;
+            let dropped_keys: Vec<(String, String)> = cache
+                .get_store()
+                .keys()
+                .filter(|(_, prefix)| is_of_products(prefix, products))
+                .cloned()
+                .collect();
+            for key in &dropped_keys {
+                cache.cache_remove(key);
+            }
+            dropped_keys
+                .into_iter()
+                .map(|(_, prefix)| prefix)
+                .collect::<BTreeSet<_>>()
+        };
+        drop_checksum_files(products).await;
+        let config = {
+            let _guard = self.config_cache.refresh_lock.lock().await;
+            self.refresh_config(products).await?
+        };
+        for prefix in dropped_prefixes {
+            self.get_mirror_listings(&prefix).await;
+        }
+        Ok(config)
+    }
+
+    /// Loads the configuration from the endpoints and updates the cache. Included files are
+    /// loaded from the endpoints again for the given products, or all products if None, and
+    /// taken from the cache otherwise.
+    async fn refresh_config(
+        &self,
+        reload_includes: Option<&[String]>,
+    ) -> Result<ProductsConfig, StorageError> {
+        let (raw, raw_config) = self
+            .try_with_endpoints(|key, backend| async move {
+                debug!("Loading products.yml for {}", key);
+                let raw = backend.fetch_config().await?;
+                let config: RawProductsConfig = serde_yaml::from_str(&raw)?;
+                Ok((raw, config))
+            })
+            .await?;
+        {
+            let mut cache = _IMPL_GET_INCLUDED_PRODUCT.lock().await;
+            for (product, path) in raw_config.includes() {
+                if reload_includes.is_none_or(|products| products.iter().any(|p| p == product)) {
+                    cache.cache_remove(&path.to_string());
+                }
+            }
+        }
+        let mut included = HashMap::new();
+        for (_, path) in raw_config.includes() {
+            if !included.contains_key(path) {
+                included.insert(
+                    path.to_string(),
+                    _impl_get_included_product(self, path).await?,
+                );
+            }
+        }
+        let mut config = raw_config.into_config(&included);
+        self.discover_versions(&mut config).await;
+        self.config_cache.set(config.clone());
+        if let Err(err) = persist_file(PRODUCTS_YML, &raw).await {
+            warn!("Failed to persist products.yml to disk: {}", err);
+        }
+        Ok(config)
+    }
+
+    /// Replaces the versions of products that discover their versions from the bucket listing
+    /// with the discovered versions, sorted by the product's version scheme.
+    async fn discover_versions(&self, config: &mut ProductsConfig) {
+        let global_scheme = config.version_scheme;
+        for (key, product) in config.products.iter_mut() {
+            let Some(patterns) = &product.discover else {
+                continue;
+            };
+            match self.get_mirror_listings(&format!("{}/", key)).await {
+                Some(listings) => {
+                    product.versions = discover_versions(
+                        key,
+                        patterns,
+                        &listings,
+                        mem::take(&mut product.versions),
+                    );
+                    product
+                        .versions
+                        .sort(product.version_scheme.unwrap_or(global_scheme));
+                }
+                None => warn!(
+                    "Could not discover versions of {}, no endpoint provides a listing.",
+                    key
+                ),
+            }
+        }
+    }
+
+    fn spawn_config_refresh(&self) {
+        let slf = self.clone();
+        tokio::spawn(async move {
+            // If a refresh is already running, there is nothing to do.
+            if let Ok(_guard) = slf.config_cache.refresh_lock.try_lock() {
+                if let Err(err) = slf.refresh_config(Some(&[])).await {
+                    warn!(
+                        "Failed to refresh products.yml, keeping last known good version: {}",
+                        err
+                    );
+                    slf.config_cache.mark_refresh_failed();
+                }
+            }
+        });
+    }
+
+    /// Returns the listings of all objects with keys starting with `prefix` (usually
+    /// `product/version/`) of every endpoint, or None, if no endpoint provides a listing.
+    /// The result may be cached. If no listing can be retrieved a warning will be logged on the
+    /// first call to this function.
+    pub async fn get_mirror_listings(&self, prefix: &str) -> Option<MirrorListings> {
+        let (listings, unlisted) = self.list_endpoints(prefix).await;
+        if !listings.is_empty() {
+            return Some(listings);
+        }
+        let guard = self.bucket_list_error_logged.lock().unwrap();
+        let mut bucket_list_error_logged = guard.borrow_mut();
+        if !*bucket_list_error_logged {
+            warn!(
+                "The endpoints do not provide a bucket listing. \
+                 Some information, like file sizes and modification dates, will not be \
+                 available: {}.",
+                unlisted
+                    .iter()
+                    .map(|(key, err)| format!("{}: {}", key, err))
+                    .join(", ")
+            );
+            *bucket_list_error_logged = true;
+        }
+        None
+    }
+
+    /// Returns the checksums from the checksum files of a version (see
+    /// `Product::checksum_files`), by path of the file relative to the root of the endpoints.
+    /// The result may be cached.
+    pub async fn get_checksums(
+        &self,
+        product_key: &str,
+        version_name: &str,
+        checksum_files: &[String],
+    ) -> HashMap<String, Checksums> {
+        let mut checksums = HashMap::new();
+        for checksum_file in checksum_files {
+            let path = format!("{}/{}/{}", product_key, version_name, checksum_file);
+            for (file_name, file_checksums) in _impl_get_checksum_file(self, &path).await {
+                let path = format!("{}/{}/{}", product_key, version_name, file_name);
+                let merged = file_checksums.or(checksums.get(&path));
+                checksums.insert(path, merged);
+            }
+        }
+        checksums
+    }
+
+    /// Compares the listings of all objects with keys starting with `prefix` of all endpoints
+    /// and reports objects that are missing on some of them or differ between them.
+    /// Uses cached listings, if available.
+    pub async fn mirror_report(&self, prefix: &str) -> MirrorReport {
+        let (listings, unlisted) = self.list_endpoints(prefix).await;
+        listings.report(unlisted)
+    }
+
+    /// Lists every available endpoint. Returns the listings and the reasons why the other
+    /// endpoints could not be listed, by endpoint key.
+    async fn list_endpoints(&self, prefix: &str) -> (MirrorListings, BTreeMap<String, String>) {
+        let mut listings = IndexMap::new();
+        let mut unlisted = BTreeMap::new();
+        for endpoint in self.endpoints.get_all() {
+            if !self.health(&endpoint.key).is_available() {
+                unlisted.insert(endpoint.key.clone(), "endpoint is unavailable".to_string());
+                continue;
+            }
+            let backend = self.backends[&endpoint.key].as_ref();
+            match _impl_get_bucket_index(backend, &endpoint.key, prefix).await {
+                Ok(index) => {
+                    listings.insert(endpoint.key.clone(), index);
+                }
+                Err(err) => {
+                    debug!("Failed to list endpoint {}: {}", endpoint.key, err);
+                    unlisted.insert(endpoint.key.clone(), err.to_string());
+                }
+            }
+        }
+        (MirrorListings::new(listings), unlisted)
+    }
+
+    /// Calls `cb` with the backends of the available endpoints (see `available_endpoints`)
+    /// in order, until one of them returns a result.
+    async fn try_with_endpoints<'a, T, F, Fut>(&'a self, cb: F) -> Result<T, StorageError>
+    where
+        F: Fn(&'a str, &'a dyn StorageBackend) -> Fut,
+        Fut: Future<Output = Result<T, StorageError>> + 'a,
+        T: 'static,
+    {
+        let mut last_error = None;
+        for endpoint in self.available_endpoints() {
+            match cb(&endpoint.key, self.backends[&endpoint.key].as_ref()).await {
+                Ok(ok) => return Ok(ok),
+                Err(err) => {
+                    warn!("failed endpoint request, triyng next if available.");
+                    last_error = Some(err);
+                }
+            }
+        }
+        error!("failed endpoint request, no more endpoints.");
+        Err(last_error.unwrap())
+    }
+}
+
+/// Loads the last copy of the configuration that was persisted to disk, with its included files.
+async fn load_persisted_config() -> Option<ProductsConfig> {
+    let raw_config: RawProductsConfig =
+        serde_yaml::from_str(&load_persisted_file(PRODUCTS_YML).await?).ok()?;
+    let mut included = HashMap::new();
+    for (_, path) in raw_config.includes() {
+        let IncludedProduct(product) =
+            serde_yaml::from_str(&load_persisted_file(path).await?).ok()?;
+        included.insert(path.to_string(), product);
+    }
+    Some(raw_config.into_config(&included))
+}
+
+#[cached(
+    ty = "TimedCache<String, Product>",
+    create = "{ TimedCache::with_lifespan_and_refresh(900, false) }",
+    sync_writes = true,
+    result = true,
+    convert = r##"{ path.to_string() }"##
+)]
+async fn _impl_get_included_product(
+    storage: &Storage,
+    path: &str,
+) -> Result<Product, StorageError> {
+    let path = path.to_string();
+    let (raw, product) = storage
+        .try_with_endpoints(|key, backend| {
+            let path = path.clone();
+            async move {
+                debug!("Loading {} for {}", path, key);
+                let raw = backend.fetch_file(&path).await?;
+                let IncludedProduct(product) = serde_yaml::from_str(&raw)?;
+                Ok((raw, product))
+            }
+        })
+        .await?;
+    if let Err(err) = persist_file(&path, &raw).await {
+        warn!("Failed to persist {} to disk: {}", path, err);
+    }
+    Ok(product)
+}
+
+/// Checksum files are optional, so a missing file is treated as empty. Files that could not be
+/// loaded are treated as empty too, so they are not requested again until the cache expires.
+#[cached(
+    ty = "TimedCache<String, HashMap<String, Checksums>>",
+    create = "{ TimedCache::with_lifespan_and_refresh(900, false) }",
+    sync_writes = true,
+    convert = r##"{ path.to_string() }"##
+)]
+async fn _impl_get_checksum_file(storage: &Storage, path: &str) -> HashMap<String, Checksums> {
+    for endpoint in storage.available_endpoints() {
+        debug!("Loading {} for {}", path, endpoint.key);
+        match storage.backends[&endpoint.key].fetch_file(path).await {
+            Ok(raw) => return parse_checksum_file(&raw),
+            Err(err) if err.is_not_found() => return HashMap::new(),
+            Err(err) => warn!(
+                "Failed to load checksum file {} from {}: {}",
+                path, endpoint.key, err
+            ),
+        }
+    }
+    HashMap::new()
+}
+
+/// Drops the cached checksum files of the given products, or of all products if None.
+async fn drop_checksum_files(products: Option<&[String]>) {
+    let mut cache = _IMPL_GET_CHECKSUM_FILE.lock().await;
+    let dropped: Vec<String> = cache
+        .get_store()
+        .keys()
+        .filter(|path| is_of_products(path, products))
+        .cloned()
+        .collect();
+    for path in &dropped {
+        cache.cache_remove(path);
+    }
+}
+
+/// Whether a path relative to the root of the endpoints is in the directory of one of the
+/// products, or of any product if None.
+fn is_of_products(path: &str, products: Option<&[String]>) -> bool {
+    products.is_none_or(|products| {
+        products
+            .iter()
+            .any(|product| path.starts_with(&format!("{}/", product)))
+    })
+}
+
+#[cached(
+    ty = "TimedCache<(String, String), BucketIndex>",
+    create = "{ TimedCache::with_lifespan_and_refresh(900, false) }",
+    sync_writes = true,
+    result = true,
+    convert = r##"{ (endpoint_key.to_string(), prefix.to_string()) }"##
+)]
+async fn _impl_get_bucket_index(
+    backend: &dyn StorageBackend,
+    endpoint_key: &str,
+    prefix: &str,
+) -> Result<BucketIndex, StorageError> {
+    debug!(
+        "Loading bucket listing for {} with prefix '{}'",
+        endpoint_key, prefix
+    );
+    Ok(backend.list_objects(prefix).await?.into_iter().collect())
+}
+
+#[derive(Error, Debug)]
+pub enum StorageError {
+    #[error("Error during request: {0}")]
+    Reqwest(#[from] reqwest::Error),
+    #[error("Error while reading YAML: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+    #[error("I/O error: {0}")]
+    IOError(#[from] io::Error),
+    #[cfg(feature = "s3_bucket_list")]
+    #[error("S3Error: {0}")]
+    S3Error(#[from] S3Error),
+    #[error("The endpoint does not provide a bucket listing.")]
+    ListingNotSupported,
+}
+
+impl StorageError {
+    /// Whether the error is caused by a file that does not exist.
+    pub fn is_not_found(&self) -> bool {
+        match self {
+            StorageError::Reqwest(err) => err.status() == Some(reqwest::StatusCode::NOT_FOUND),
+            StorageError::IOError(err) => err.kind() == io::ErrorKind::NotFound,
+            #[cfg(feature = "s3_bucket_list")]
+            StorageError::S3Error(S3Error::HttpFailWithBody(status, _)) => *status == 404,
+            _ => false,
+        }
+    }
+}
+
+/// The product configuration, with all included products loaded.
+#[derive(Debug, Clone)]
+pub struct ProductsConfig {
+    pub products: IndexMap<String, Product>,
+    pub banner: Option<Banner>,
+    pub pre_release_patterns: Vec<PreReleasePatternEntry>,
+    pub channels: Vec<ChannelEntry>,
+    pub version_scheme: VersionScheme,
+    /// Paths of the files of included products, by product key.
+    pub includes: IndexMap<String, String>,
+}
+
+impl ProductsConfig {
+    /// Removes the products and versions that are not published at the given time.
+    pub fn retain_published(&mut self, now: DateTime<Utc>) {
+        self.products
+            .retain(|_, product| is_published(product.publish_at, now));
+        for product in self.products.values_mut() {
+            product
+                .versions
+                .retain(|_, info| is_published(info.publish_at, now));
+        }
+    }
+
+    /// Finds a product by a key from a URL and returns it with its canonical key. Besides the
+    /// key itself, keys that only differ in case and the product's aliases match.
+    pub fn find_product(&self, key: &str) -> Option<(&str, &Product)> {
+        if let Some((canonical, product)) = self.products.get_key_value(key) {
+            return Some((canonical, product));
+        }
+        let found = self
+            .products
+            .iter()
+            .find(|(canonical, _)| canonical.eq_ignore_ascii_case(key))
+            .or_else(|| {
+                self.products.iter().find(|(_, product)| {
+                    product
+                        .aliases
+                        .iter()
+                        .any(|alias| alias.eq_ignore_ascii_case(key))
+                })
+            });
+        found.map(|(canonical, product)| (canonical.as_str(), product))
+    }
+
+    /// Returns the keys of the products an object in the bucket belongs to: The products included
+    /// from the object, or the product of the directory the object is in (see `find_product`).
+    /// Returns None for objects that don't belong to a product.
+    pub fn products_of_object(&self, object_key: &str) -> Option<Vec<&str>> {
+        let included: Vec<&str> = self
+            .includes
+            .iter()
+            .filter(|(_, path)| path.as_str() == object_key)
+            .map(|(product, _)| product.as_str())
+            .collect();
+        if !included.is_empty() {
+            return Some(included);
+        }
+        let (directory, _) = object_key.split_once('/')?;
+        self.find_product(directory).map(|(key, _)| vec![key])
+    }
+}
+
+fn is_published(publish_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+    publish_at.is_none_or(|publish_at| publish_at <= now)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Banner {
+    pub url_file: String,
+    pub image_file: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PreReleasePatternEntry {
+    #[serde(with = "serde_regex")]
+    pub pattern: Regex,
+    pub display_name: String,
+}
+
+impl PartialEq for PreReleasePatternEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern.as_str() == other.pattern.as_str() && self.display_name == other.display_name
+    }
+}
+
+impl Eq for PreReleasePatternEntry {}
+
+/// A release channel (like `beta` or `lts`). Versions are in the channel if they set it as their
+/// `channel` or if their name matches the pattern. See `channel::Channels`. Whether a version is a
+/// pre-release is still decided by the pre-release patterns.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChannelEntry {
+    pub key: String,
+    pub display_name: String,
+    #[serde(default, with = "serde_regex")]
+    pub pattern: Option<Regex>,
+}
+
+impl PartialEq for ChannelEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+            && self.display_name == other.display_name
+            && self.pattern.as_ref().map(Regex::as_str) == other.pattern.as_ref().map(Regex::as_str)
+    }
+}
+
+impl Eq for ChannelEntry {}
+
+/// Deserialized by the `Deserialize` impl below, which checks that `versions` is only left out
+/// by products that discover their versions.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(remote = "Self")]
+pub struct Product {
+    pub name: String,
+    #[serde(default)]
+    pub icon_path: Option<String>,
+    /// Former keys of the product. URLs with an alias are redirected to the product's key.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// The products page groups the products by category. Products without a category are
+    /// listed first.
+    #[serde(default)]
+    pub category: Option<String>,
+    /// A short description shown on the products page.
+    #[serde(default)]
+    pub tagline: Option<String>,
+    /// Featured products are highlighted and listed first in their category.
+    #[serde(default)]
+    pub featured: bool,
+    /// Products with a lower weight are listed first in their category. Products with the
+    /// same weight are listed in the order of the configuration.
+    #[serde(default)]
+    pub sort_weight: i64,
+    #[serde(default)]
+    pub settings: HashMap<ArtifactKey, Value>,
+    /// File names of downloads by artifact type, which every version of the product uses,
+    /// unless it lists the artifact type with a different file name or as `null`.
+    /// See `expand_download_templates`.
+    #[serde(default)]
+    pub download_templates: IndexMap<ArtifactKey, String>,
+    /// Checksum files (like `SHA256SUMS`) in the directory of every version, in the format of
+    /// `sha256sum` (see `checksums::parse_checksum_file`). Downloads without checksums in their
+    /// attributes use the checksums from these files.
+    #[serde(default)]
+    pub checksum_files: Vec<String>,
+    /// If set, versions and their downloads are discovered from the bucket listing
+    /// (see `discovery::discover_versions`) and `versions` only needs to contain overrides.
+    #[serde(default)]
+    pub discover: Option<Vec<DiscoveryPattern>>,
+    /// How the versions are ordered, which decides the latest version. Defaults to the global
+    /// `version_scheme` of `products.yml`.
+    #[serde(default)]
+    pub version_scheme: Option<VersionScheme>,
+    /// Pre-release patterns of the product, checked before the global patterns.
+    #[serde(default)]
+    pub pre_release_patterns: Vec<PreReleasePatternEntry>,
+    /// If set, only the product's own pre-release patterns are used.
+    #[serde(default)]
+    pub override_pre_release_patterns: bool,
+    /// Release channels of the product. They take precedence over the global channels with the
+    /// same key.
+    #[serde(default)]
+    pub channels: Vec<ChannelEntry>,
+    /// Required, unless the product discovers its versions.
+    #[serde(default)]
+    pub versions: ReleaseMap,
+    #[serde(default)]
+    pub nightly: Option<NightlyConfig>,
+    /// The product is treated as if it did not exist before this time (RFC 3339).
+    #[serde(default)]
+    pub publish_at: Option<DateTime<Utc>>,
+}
+
+/// Key of the versions of a product.
+const VERSIONS_KEY: &str = "versions";
+
+impl<'de> Deserialize<'de> for Product {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(ProductVisitor)
+    }
+}
+
+struct ProductVisitor;
+
+impl<'de> Visitor<'de> for ProductVisitor {
+    type Value = Product;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a product")
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut has_versions = false;
+        // The inherent `deserialize` derived with `remote = "Self"`.
+        let product = Product::deserialize(de::value::MapAccessDeserializer::new(
+            VersionsKeyMapAccess {
+                map,
+                has_versions: &mut has_versions,
+            },
+        ))?;
+        if product.discover.is_none() {
+            if !has_versions {
+                return Err(de::Error::missing_field(VERSIONS_KEY));
+            }
+            // Only versions that are discovered get their date from the bucket listing.
+            if let Some(version) = product
+                .versions
+                .map()
+                .iter()
+                .find_map(|(version, info)| info.date.is_missing().then_some(version))
+            {
+                return Err(de::Error::custom(format_args!(
+                    "missing field `date` in version {}",
+                    version
+                )));
+            }
+        }
+        Ok(product)
+    }
+}
+
+/// Map access that records whether the map has a `versions` key.
+struct VersionsKeyMapAccess<'a, A> {
+    map: A,
+    has_versions: &'a mut bool,
+}
+
+impl<'de, A> MapAccess<'de> for VersionsKeyMapAccess<'_, A>
+where
+    A: MapAccess<'de>,
+{
+    type Error = A::Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.map.next_key::<String>()? {
+            Some(key) => {
+                *self.has_versions |= key == VERSIONS_KEY;
+                seed.deserialize(key.into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        self.map.next_value_seed(seed)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct VersionInfo {
+    /// Required, unless the product discovers its versions from the bucket listing, which
+    /// gives discovered versions a date. See `ReleaseDate` for the accepted formats.
+    #[serde(default)]
+    pub date: ReleaseDate,
+    /// Former names of the version. URLs with an alias are redirected to the version's name.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// The release channel of the version, instead of the one matched by its name.
+    #[serde(default)]
+    pub channel: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub changelog: Option<String>,
+    #[serde(default)]
+    pub changelog_section: Option<u64>,
+    /// The downloads of the version by artifact type. An artifact type can have a single
+    /// download or a list of downloads, which are told apart by their `label`.
+    #[serde(default, deserialize_with = "deserialize_downloads")]
+    pub downloads: IndexMap<ArtifactKey, Vec<DownloadSpec>>,
+    /// If set, the version was pulled. It stays reachable by its URL, but its downloads are
+    /// not offered by default.
+    #[serde(default)]
+    pub yanked: Option<Yanked>,
+    /// Whether and how long the version is supported.
+    #[serde(default)]
+    pub support: Option<Support>,
+    /// Hidden versions are only reachable by their URL.
+    #[serde(default)]
+    pub hidden: bool,
+    /// The version is treated as if it did not exist before this time (RFC 3339).
+    #[serde(default)]
+    pub publish_at: Option<DateTime<Utc>>,
+}
+
+impl VersionInfo {
+    /// Whether the version is listed and can be the latest version. Yanked and hidden versions
+    /// are not listed.
+    pub fn is_listed(&self) -> bool {
+        self.yanked.is_none() && !self.hidden
+    }
+
+    /// The current support status of the version, if known.
+    pub fn support_status(&self) -> Option<SupportStatus> {
+        self.support
+            .as_ref()
+            .map(|support| support.status_at(Utc::now()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Yanked {
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Support {
+    pub status: SupportStatus,
+    /// When the status ends. Versions are end-of-life after this date.
+    #[serde(default)]
+    pub until: ReleaseDate,
+}
+
+impl Support {
+    /// The status at the given time, taking the end date into account.
+    pub fn status_at(&self, now: DateTime<Utc>) -> SupportStatus {
+        match self.until.date_time() {
+            Some(until) if until < now => SupportStatus::Eol,
+            _ => self.status,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SupportStatus {
+    /// Long-term support.
+    Lts,
+    Supported,
+    /// Only security issues are fixed.
+    SecurityOnly,
+    /// End of life, not supported anymore.
+    Eol,
+}
+
+impl SupportStatus {
+    /// The status as written in `products.yml`, for CSS classes.
+    pub fn key(&self) -> &'static str {
+        match self {
+            SupportStatus::Lts => "lts",
+            SupportStatus::Supported => "supported",
+            SupportStatus::SecurityOnly => "security-only",
+            SupportStatus::Eol => "eol",
+        }
+    }
+
+    pub fn is_eol(&self) -> bool {
+        matches!(self, SupportStatus::Eol)
+    }
+}
+
+impl fmt::Display for SupportStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SupportStatus::Lts => "LTS",
+            SupportStatus::Supported => "Supported",
+            SupportStatus::SecurityOnly => "Security fixes only",
+            SupportStatus::Eol => "End of life",
+        })
+    }
+}
+
+const DOWNLOAD_ATTRIBUTE_UNSUPPORTED: &str = "unsupported";
+const DOWNLOAD_ATTRIBUTE_LABEL: &str = "label";
+/// Download attributes with the path of a detached signature of the file, by signature format.
+/// The path is relative to the directory of the version, like the download's URL.
+pub const DOWNLOAD_ATTRIBUTES_SIGNATURE: &[&str] = &["sig", "minisig", "asc"];
+/// URL of downloads that use the download template of their artifact type.
+pub const DOWNLOAD_TEMPLATE_DEFAULT: &str = "default";
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum DownloadSpec {
+    Url(String),
+    Complex {
+        url: String,
+        #[serde(flatten)]
+        attributes: IndexMap<String, Value>,
+    },
+    Null,
+}
+
+impl DownloadSpec {
+    pub fn url(&self) -> &str {
+        match self {
+            DownloadSpec::Url(url) => url,
+            DownloadSpec::Null => "",
+            DownloadSpec::Complex { url, .. } => url,
+        }
+    }
+
+    pub fn is_unsupported(&self) -> bool {
+        match self {
+            DownloadSpec::Complex { attributes, .. } => {
+                if let Some(v) = attributes.get(DOWNLOAD_ATTRIBUTE_UNSUPPORTED) {
+                    matches!(v, Value::Bool(true))
+                } else {
+                    false
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// The checksums given in the download's attributes.
+    pub fn checksums(&self) -> Checksums {
+        let attribute = |name| match self {
+            DownloadSpec::Complex { attributes, .. } => attributes
+                .get(name)
+                .and_then(Value::as_str)
+                .map(str::to_ascii_lowercase),
+            _ => None,
+        };
+        Checksums {
+            sha256: attribute(CHECKSUM_SHA256),
+            sha512: attribute(CHECKSUM_SHA512),
+        }
+    }
+
+    /// The format and the path of the download's detached signature, if it has one.
+    pub fn signature(&self) -> Option<(&'static str, &str)> {
+        match self {
+            DownloadSpec::Complex { attributes, .. } => {
+                DOWNLOAD_ATTRIBUTES_SIGNATURE.iter().find_map(|format| {
+                    attributes
+                        .get(*format)
+                        .and_then(Value::as_str)
+                        .map(|path| (*format, path))
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Attributes that are not used by any feature, most likely misspelled.
+    pub fn unknown_attributes(&self) -> impl Iterator<Item = &str> {
+        let attributes = match self {
+            DownloadSpec::Complex { attributes, .. } => Some(attributes),
+            _ => None,
+        };
+        attributes
+            .into_iter()
+            .flat_map(IndexMap::keys)
+            .map(String::as_str)
+            .filter(|name| {
+                ![
+                    DOWNLOAD_ATTRIBUTE_UNSUPPORTED,
+                    DOWNLOAD_ATTRIBUTE_LABEL,
+                    CHECKSUM_SHA256,
+                    CHECKSUM_SHA512,
+                ]
+                .contains(name)
+                    && !DOWNLOAD_ATTRIBUTES_SIGNATURE.contains(name)
+            })
+    }
+
+    /// The label that tells the download apart from other downloads of the same artifact type,
+    /// like "Portable" or "Debug symbols".
+    pub fn label(&self) -> Option<&str> {
+        match self {
+            DownloadSpec::Complex { attributes, .. } => attributes
+                .get(DOWNLOAD_ATTRIBUTE_LABEL)
+                .and_then(Value::as_str),
+            _ => None,
+        }
+    }
+}
+
+fn deserialize_downloads<'de, D>(
+    deserializer: D,
+) -> Result<IndexMap<ArtifactKey, Vec<DownloadSpec>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        Many(Vec<DownloadSpec>),
+        One(DownloadSpec),
+    }
+
+    Ok(
+        IndexMap::<ArtifactKey, OneOrMany>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(key, downloads)| match downloads {
+                OneOrMany::Many(downloads) => (key, downloads),
+                OneOrMany::One(download) => (key, vec![download]),
+            })
+            .collect(),
+    )
+}
+
+/// Returns the downloads of a version with the product's download templates applied: Downloads
+/// with the URL `default` and artifact types the version does not list use the template of their
+/// artifact type. The placeholders `{product}`, `{version}` and `{version_no_v}` (the version
+/// without a leading `v`) in templates are replaced. A version can leave out the download of a
+/// template by listing its artifact type as `null`.
+pub fn expand_download_templates(
+    downloads: &IndexMap<ArtifactKey, Vec<DownloadSpec>>,
+    download_templates: &IndexMap<ArtifactKey, String>,
+    product_name: &str,
+    version_name: &str,
+) -> IndexMap<ArtifactKey, Vec<DownloadSpec>> {
+    let expand = |key: &str| {
+        download_templates.get(key).map(|template| {
+            template
+                .replace("{product}", product_name)
+                .replace("{version}", version_name)
+                .replace(
+                    "{version_no_v}",
+                    version_name.strip_prefix('v').unwrap_or(version_name),
+                )
+        })
+    };
+    let expand_one = |key: &str, download: &DownloadSpec| match (download, expand(key)) {
+        (DownloadSpec::Url(url), Some(expanded)) if url == DOWNLOAD_TEMPLATE_DEFAULT => {
+            DownloadSpec::Url(expanded)
+        }
+        (DownloadSpec::Complex { url, attributes }, Some(expanded))
+            if url == DOWNLOAD_TEMPLATE_DEFAULT =>
+        {
+            DownloadSpec::Complex {
+                url: expanded,
+                attributes: attributes.clone(),
+            }
+        }
+        _ => download.clone(),
+    };
+    let mut expanded: IndexMap<ArtifactKey, Vec<DownloadSpec>> = downloads
+        .iter()
+        .filter(|(key, downloads)| {
+            !(download_templates.contains_key(*key) && downloads[..] == [DownloadSpec::Null])
+        })
+        .map(|(key, downloads)| {
+            let downloads = downloads
+                .iter()
+                .map(|download| expand_one(key, download))
+                .collect();
+            (key.clone(), downloads)
+        })
+        .collect();
+    for key in download_templates.keys() {
+        if !downloads.contains_key(key) {
+            if let Some(url) = expand(key) {
+                expanded.insert(key.clone(), vec![DownloadSpec::Url(url)]);
+            }
+        }
+    }
+    expanded
+}
+
+impl<'a> From<&'a VersionInfo> for Cow<'a, VersionInfo> {
+    fn from(v: &'a VersionInfo) -> Self {
+        Cow::Borrowed(v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_of_products() {
+        let products = ["tool".to_string()];
+        assert!(is_of_products("tool/1.0.0/SHA256SUMS", Some(&products)));
+        assert!(!is_of_products(
+            "tool-extra/1.0.0/SHA256SUMS",
+            Some(&products)
+        ));
+        assert!(!is_of_products("tool", Some(&products)));
+        assert!(!is_of_products("tool/1.0.0/SHA256SUMS", Some(&[])));
+        assert!(is_of_products("other/1.0.0/SHA256SUMS", None));
+    }
+
+    #[rocket::async_test]
+    async fn refresh_drops_checksum_files_of_products() {
+        let paths = [
+            "tool/1.0.0/SHA256SUMS",
+            "tool/1.1.0/SHA512SUMS",
+            "other/1.0.0/SHA256SUMS",
+        ];
+        let cached_paths = || async {
+            let mut cache = _IMPL_GET_CHECKSUM_FILE.lock().await;
+            paths
+                .iter()
+                .filter(|path| cache.cache_get(&path.to_string()).is_some())
+                .copied()
+                .collect::<Vec<_>>()
+        };
+        {
+            let mut cache = _IMPL_GET_CHECKSUM_FILE.lock().await;
+            for path in paths {
+                cache.cache_set(path.to_string(), HashMap::new());
+            }
+        }
+        drop_checksum_files(Some(&["tool".to_string()])).await;
+        assert_eq!(cached_paths().await, ["other/1.0.0/SHA256SUMS"]);
+        drop_checksum_files(None).await;
+        assert!(cached_paths().await.is_empty());
+    }
+}
@@ -4,7 +4,9 @@ use askama::Template;
 use indexmap::IndexMap;
 
 use crate::r#impl::artifacttype::RenderableArtifact;
-use crate::r#impl::storage::{PreReleasePatternEntry, Product};
+use crate::r#impl::channel::{ChannelGroup, Channels};
+use crate::r#impl::release_date::ReleaseDate;
+use crate::r#impl::storage::{ChannelEntry, PreReleasePatternEntry, Product, SupportStatus};
 
 #[derive(Template)]
 #[template(path = "p_404.html")]
@@ -29,7 +31,21 @@ pub struct TemplateProducts<'a> {
     pub theme_name: Cow<'a, str>,
     pub home_url: Cow<'a, str>,
     pub default_endpoint_url: Cow<'a, str>,
-    pub products: IndexMap<String, Product>,
+    pub categories: Vec<ProductCategory>,
+}
+
+/// The products of a category on the products page, in the order they are listed.
+pub struct ProductCategory {
+    /// None for the products without a category.
+    pub name: Option<String>,
+    pub products: Vec<ProductListEntry>,
+}
+
+pub struct ProductListEntry {
+    pub key: String,
+    pub product: Product,
+    pub latest_version: Option<String>,
+    pub latest_date: ReleaseDate,
 }
 
 #[derive(Template)]
@@ -42,9 +58,23 @@ pub struct TemplateReleases<'a> {
     pub product_key: Cow<'a, str>,
     pub product: Product,
     pub pre_release_patterns: Vec<PreReleasePatternEntry>,
+    /// The global release channels.
+    pub channels: Vec<ChannelEntry>,
+    /// If set, only the releases of this channel are listed, as key and display name.
+    pub channel: Option<(String, String)>,
     pub has_nightly: bool,
 }
 
+impl TemplateReleases<'_> {
+    /// The listed versions, grouped by channel.
+    pub fn channel_groups(&self) -> Vec<ChannelGroup<'_>> {
+        Channels::new(&self.product, &self.channels, &self.pre_release_patterns).group(
+            &self.product.versions,
+            self.channel.as_ref().map(|(channel, _)| channel.as_str()),
+        )
+    }
+}
+
 #[derive(Template)]
 #[template(path = "p_release.html")]
 pub struct TemplateRelease<'a> {
@@ -59,14 +89,22 @@ pub struct TemplateRelease<'a> {
     pub product_version: Cow<'a, str>,
     pub product_version_prev: Option<Cow<'a, str>>,
     pub product_version_next: Option<Cow<'a, str>>,
-    pub release_date: Cow<'a, str>,
+    pub release_date: ReleaseDate,
+    /// Reason why the release was yanked, if it was.
+    pub yanked_reason: Option<Cow<'a, str>>,
+    pub support_status: Option<SupportStatus>,
+    /// When the support status ends, if known.
+    pub support_until: ReleaseDate,
+    /// The latest version, if it is not this version.
+    pub latest_version: Option<Cow<'a, str>>,
     pub product_icon: Option<Cow<'a, str>>,
     pub description: Option<Cow<'a, str>>,
     pub extra_description: IndexMap<Cow<'a, str>, Cow<'a, str>>,
     pub pre_release: Option<Cow<'a, str>>,
     pub downloads: DownloadGridTemplate<'a>,
     pub downloads_unsupported: Option<DownloadGridTemplate<'a>>,
-    pub endpoints: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+    /// Key, display name and availability of all endpoints.
+    pub endpoints: Vec<(Cow<'a, str>, Cow<'a, str>, bool)>,
     pub auto_endpoint: Cow<'a, str>,
     pub translate_note_text_en: Option<Cow<'a, str>>,
     pub translate_note_text: Option<Cow<'a, str>>,
@@ -101,7 +139,7 @@ mod filters {
     use std::borrow::Cow;
     use std::collections::BTreeMap;
 
-    use chrono::{TimeZone, Utc};
+    use chrono::{SecondsFormat, TimeZone, Utc};
     use relativetime::RelativeTime;
 
     pub fn endpoint_links(
@@ -111,14 +149,40 @@ mod filters {
         let mut out = Vec::with_capacity(urls.len() + 1);
         out.push(format!(
             "href=\"{}\"",
-            urls.get(auto_endpoint).cloned().unwrap_or_else(|| urls
-                .values()
-                .next()
-                .cloned()
-                .unwrap_or_else(|| "#".into()))
+            escape_url(
+                &urls.get(auto_endpoint).cloned().unwrap_or_else(|| urls
+                    .values()
+                    .next()
+                    .cloned()
+                    .unwrap_or_else(|| "#".into()))
+            )
         ));
+        // Links without URLs of any mirror are not switched by the mirror selection.
+        if !urls.is_empty() {
+            out.push("data-mirror-link".to_string());
+        }
         for (k, url) in urls {
-            out.push(format!("data-href-{}=\"{}\"", k.to_lowercase(), url))
+            out.push(format!(
+                "data-href-{}=\"{}\"",
+                k.to_lowercase(),
+                escape_url(url)
+            ))
+        }
+        Ok(out.join(" "))
+    }
+
+    /// Escapes a URL for use in an attribute. Presigned URLs contain query strings.
+    fn escape_url(url: &str) -> String {
+        url.replace('&', "&amp;").replace('"', "&quot;")
+    }
+
+    /// Renders the per-endpoint values of a file's metadata as data attributes, so the
+    /// mirror selection can show the values of the selected mirror.
+    pub fn mirror_values(values: BTreeMap<&str, &str>) -> askama::Result<String> {
+        let mut out = Vec::with_capacity(values.len() + 1);
+        out.push("data-mirror-value".to_string());
+        for (k, value) in values {
+            out.push(format!("data-value-{}=\"{}\"", k.to_lowercase(), value))
         }
         Ok(out.join(" "))
     }
@@ -128,8 +192,42 @@ mod filters {
         Ok(date_time.format("%Y-%m-%d %H:%M:%S UTC").to_string())
     }
 
+    pub fn isotime(timestamp: &&i64) -> askama::Result<String> {
+        let date_time = Utc.timestamp_opt(**timestamp, 0).unwrap();
+        Ok(date_time.to_rfc3339_opts(SecondsFormat::Secs, true))
+    }
+
     pub fn reltime(timestamp: &&i64) -> askama::Result<String> {
         let date_time = Utc.timestamp_opt(**timestamp, 0).unwrap();
         Ok(date_time.to_relative())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    #[test]
+    fn endpoint_links_of_mirrors() {
+        let urls = BTreeMap::from([
+            ("EU".into(), "https://eu.example.com/a?x=1&y=2".into()),
+            ("US".into(), "https://us.example.com/a".into()),
+        ]);
+        assert_eq!(
+            filters::endpoint_links(&urls, "US").unwrap(),
+            "href=\"https://us.example.com/a\" data-mirror-link \
+             data-href-eu=\"https://eu.example.com/a?x=1&amp;y=2\" \
+             data-href-us=\"https://us.example.com/a\""
+        );
+    }
+
+    #[test]
+    fn endpoint_links_without_urls_are_not_mirror_links() {
+        assert_eq!(
+            filters::endpoint_links(&BTreeMap::new(), "US").unwrap(),
+            "href=\"#\""
+        );
+    }
+}
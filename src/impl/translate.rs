@@ -11,7 +11,7 @@ use indexmap::IndexMap;
 use log::{debug, warn};
 use thiserror::Error;
 
-use crate::r#impl::config::SimpleConfig;
+use crate::r#impl::config::{ConfigVars, SimpleConfig};
 
 static SUPPORTED_LANGS: &[&str] = &[
     "af", "sq", "am", "ar", "hy", "az", "bn", "bs", "bg", "ca", "zh", "zh-TW", "hr", "cs", "da",
@@ -121,9 +121,9 @@ pub(crate) struct TranslateConfig {
 }
 
 impl TranslateConfig {
-    pub fn get() -> Option<Self> {
-        let key_id = TranslateAwsKeyId::get_checked();
-        let key = TranslateAwsSecretAccessKey::get_checked();
+    pub fn get(vars: &ConfigVars) -> Option<Self> {
+        let key_id = TranslateAwsKeyId::get_checked(vars);
+        let key = TranslateAwsSecretAccessKey::get_checked(vars);
         match (key_id, key) {
             (Ok(key_id), Ok(key)) => {
                 let config = executor::block_on(Compat::new(
@@ -151,13 +151,13 @@ impl TranslateConfig {
     }
 }
 
-struct TranslateAwsKeyId {}
+pub(crate) struct TranslateAwsKeyId {}
 
 impl SimpleConfig for TranslateAwsKeyId {
     const VAR_NAME: &'static str = "DEPBOX_TRANSLATE_AWS_ACCESS_KEY_ID";
 }
 
-struct TranslateAwsSecretAccessKey {}
+pub(crate) struct TranslateAwsSecretAccessKey {}
 
 impl SimpleConfig for TranslateAwsSecretAccessKey {
     const VAR_NAME: &'static str = "DEPBOX_TRANSLATE_AWS_SECRET_ACCESS_KEY";
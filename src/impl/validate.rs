@@ -0,0 +1,709 @@
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::io;
+
+use indexmap::IndexMap;
+use reqwest::StatusCode;
+use rocket::futures::{stream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::r#impl::artifacttype::{get_artifact_info, ArtifactKey, ArtifactTypes, FALLBACK_KEY};
+use crate::r#impl::release_date::ReleaseDate;
+use crate::r#impl::storage::{
+    expand_download_templates, is_valid_checksum, DownloadSpec, IncludedProduct, Product,
+    ProductSource, ProductsConfig, RawProductsConfig, Storage, StorageBackend,
+    DOWNLOAD_TEMPLATE_DEFAULT,
+};
+
+/// A problem found while validating a product configuration.
+#[derive(Debug, Clone)]
+pub struct ValidationProblem {
+    /// The included file the problem was found in, or None for `products.yml` itself.
+    pub file: Option<String>,
+    pub location: ProblemLocation,
+    pub message: String,
+}
+
+/// Where in the product configuration a problem was found.
+#[derive(Debug, Clone)]
+pub enum ProblemLocation {
+    /// A position in the file, for problems found while parsing.
+    Position { line: usize, column: usize },
+    /// The path of keys leading to the problematic value, for problems found after parsing.
+    Path(String),
+    /// The file as a whole.
+    File,
+}
+
+impl fmt::Display for ProblemLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProblemLocation::Position { line, column } => write!(f, "{}:{}", line, column),
+            ProblemLocation::Path(path) => write!(f, "{}", path),
+            ProblemLocation::File => write!(f, "-"),
+        }
+    }
+}
+
+/// The result of validating a product configuration.
+pub struct Validation {
+    /// The parsed configuration, or None if it could not be parsed.
+    pub config: Option<ProductsConfig>,
+    pub problems: Vec<ValidationProblem>,
+}
+
+/// Parses a product configuration (`products.yml`) and the files it includes, which are read
+/// with `read_include`, and checks it for problems that would only show up when serving the
+/// products: Unknown artifact types and artifact type settings that don't have the shape the
+/// artifact types expect.
+///
+/// Pre-release patterns are compiled while parsing, so invalid patterns are reported as parse
+/// errors. Unknown keys and download attributes, which are most likely misspelled, are reported
+/// too.
+pub fn validate_products(
+    raw: &str,
+    ats: &ArtifactTypes,
+    read_include: impl Fn(&str) -> io::Result<String>,
+) -> Validation {
+    let mut unknown = Vec::new();
+    let raw_config = match parse_yaml::<RawProductsConfig>(raw, &mut unknown) {
+        Ok(raw_config) => raw_config,
+        Err(err) => {
+            return Validation {
+                config: None,
+                problems: vec![parse_problem(None, err)],
+            }
+        }
+    };
+    for (key, source) in &raw_config.products {
+        if let ProductSource::Inline(product) = source {
+            let prefix = [PathSegment::key("products"), PathSegment::key(key)];
+            unknown.extend(
+                unknown_download_attributes(product)
+                    .into_iter()
+                    .map(|path| prefix.iter().cloned().chain(path).collect()),
+            );
+        }
+    }
+    let mut problems = unknown_key_problems(None, raw, unknown);
+    let mut parse_problems = Vec::new();
+    let mut included = HashMap::new();
+    for (_, path) in raw_config.includes() {
+        if included.contains_key(path) {
+            continue;
+        }
+        let raw = match read_include(path) {
+            Ok(raw) => raw,
+            Err(err) => {
+                parse_problems.push(ValidationProblem {
+                    file: Some(path.to_string()),
+                    location: ProblemLocation::File,
+                    message: format!("Could not read included file: {}", err),
+                });
+                continue;
+            }
+        };
+        let mut unknown = Vec::new();
+        match parse_yaml::<IncludedProduct>(&raw, &mut unknown) {
+            Ok(IncludedProduct(product)) => {
+                unknown.extend(unknown_download_attributes(&product));
+                problems.extend(unknown_key_problems(Some(path), &raw, unknown));
+                included.insert(path.to_string(), product);
+            }
+            Err(err) => parse_problems.push(parse_problem(Some(path.to_string()), err)),
+        }
+    }
+    if !parse_problems.is_empty() {
+        problems.extend(parse_problems);
+        return Validation {
+            config: None,
+            problems,
+        };
+    }
+    let config = raw_config.into_config(&included);
+    problems.extend(check_products(&config, ats));
+    Validation {
+        problems,
+        config: Some(config),
+    }
+}
+
+/// Parses YAML like `serde_yaml::from_str`. The paths of keys that are ignored, because they are
+/// not part of the configuration, are added to `unknown`.
+fn parse_yaml<T: DeserializeOwned>(
+    raw: &str,
+    unknown: &mut Vec<Vec<PathSegment>>,
+) -> Result<T, serde_yaml::Error> {
+    serde_ignored::deserialize(serde_yaml::Deserializer::from_str(raw), |path| {
+        let mut segments = Vec::new();
+        push_segments(&path, &mut segments);
+        unknown.push(segments);
+    })
+}
+
+fn push_segments(path: &serde_ignored::Path, segments: &mut Vec<PathSegment>) {
+    match path {
+        serde_ignored::Path::Root => {}
+        serde_ignored::Path::Seq { parent, index } => {
+            push_segments(parent, segments);
+            segments.push(PathSegment::Index(*index));
+        }
+        serde_ignored::Path::Map { parent, key } => {
+            push_segments(parent, segments);
+            segments.push(PathSegment::Key(key.clone()));
+        }
+        serde_ignored::Path::Some { parent }
+        | serde_ignored::Path::NewtypeStruct { parent }
+        | serde_ignored::Path::NewtypeVariant { parent } => push_segments(parent, segments),
+    }
+}
+
+/// A key or an index in the path to a value in a YAML file.
+#[derive(Debug, Clone)]
+enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+impl PathSegment {
+    fn key(key: &str) -> Self {
+        PathSegment::Key(key.to_string())
+    }
+}
+
+fn display_path(path: &[PathSegment]) -> String {
+    path.iter()
+        .map(|segment| match segment {
+            PathSegment::Key(key) => key.clone(),
+            PathSegment::Index(index) => index.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Returns the paths of the download attributes of a product that are not used, relative to the
+/// product.
+fn unknown_download_attributes(product: &Product) -> Vec<Vec<PathSegment>> {
+    let mut paths = Vec::new();
+    for (version, info) in product.versions.map() {
+        for (key, downloads) in &info.downloads {
+            for (i, download) in downloads.iter().enumerate() {
+                for attribute in download.unknown_attributes() {
+                    paths.push(vec![
+                        PathSegment::key("versions"),
+                        PathSegment::key(version),
+                        PathSegment::key("downloads"),
+                        PathSegment::key(key),
+                        PathSegment::Index(i),
+                        PathSegment::key(attribute),
+                    ]);
+                }
+            }
+        }
+    }
+    for (key, download) in product.nightly.iter().flat_map(|n| n.downloads()) {
+        for attribute in download.unknown_attributes() {
+            paths.push(vec![
+                PathSegment::key("nightly"),
+                PathSegment::key("downloads"),
+                PathSegment::key(key),
+                PathSegment::key(attribute),
+            ]);
+        }
+    }
+    paths
+}
+
+fn unknown_key_problems(
+    file: Option<&str>,
+    raw: &str,
+    unknown: Vec<Vec<PathSegment>>,
+) -> Vec<ValidationProblem> {
+    unknown
+        .into_iter()
+        .filter_map(|path| {
+            let (PathSegment::Key(key), parent) = path.split_last()? else {
+                return None;
+            };
+            let message = match parent {
+                [] => format!("Unknown key '{}'.", key),
+                parent => format!("Unknown key '{}' in {}.", key, display_path(parent)),
+            };
+            let location = match find_position(raw, &path) {
+                Some((line, column)) => ProblemLocation::Position { line, column },
+                None => ProblemLocation::Path(display_path(&path)),
+            };
+            Some(ValidationProblem {
+                file: file.map(ToString::to_string),
+                location,
+                message,
+            })
+        })
+        .collect()
+}
+
+/// Finds the line and column of the value at a path in a YAML file, for problems that are found
+/// after parsing. Only block style is supported, values in flow style (`{ ... }`) are not found.
+/// An index of 0 also matches a single value that is not written as a list.
+fn find_position(raw: &str, path: &[PathSegment]) -> Option<(usize, usize)> {
+    let lines: Vec<&str> = raw.lines().collect();
+    // The value of the current path starts at `line` and `column` (the content of a list item
+    // starts after its `-`) and contains the lines indented more than `parent_indent`.
+    let mut line = 0;
+    let mut column = 0;
+    let mut parent_indent = None;
+    let mut position = None;
+    'segments: for segment in path {
+        let mut child_indent = None;
+        let mut items = 0;
+        let mut found = None;
+        for (i, text) in lines.iter().enumerate().skip(line) {
+            let start = if i == line { column } else { 0 };
+            let Some(text) = text.get(start..) else {
+                continue;
+            };
+            let content = text.trim_start();
+            if content.is_empty() || content.starts_with('#') {
+                continue;
+            }
+            let indent = start + text.len() - content.len();
+            let is_item = content == "-" || content.starts_with("- ");
+            if let Some(parent_indent) = parent_indent {
+                // Lists can be indented as much as the key they belong to.
+                let is_list_of_parent =
+                    matches!(segment, PathSegment::Index(_)) && is_item && indent == parent_indent;
+                if indent <= parent_indent && !is_list_of_parent {
+                    break;
+                }
+            }
+            if indent != *child_indent.get_or_insert(indent) {
+                continue;
+            }
+            match segment {
+                PathSegment::Key(key) if is_key(content, key) => {
+                    found = Some((i, indent, None));
+                    break;
+                }
+                PathSegment::Key(_) => {}
+                PathSegment::Index(0) if !is_item => continue 'segments,
+                PathSegment::Index(_) if !is_item => return None,
+                PathSegment::Index(index) if items == *index => {
+                    let item = &content[1..];
+                    let item_column = indent + 1 + item.len() - item.trim_start().len();
+                    found = Some((i, indent, Some(item_column)));
+                    break;
+                }
+                PathSegment::Index(_) => items += 1,
+            }
+        }
+        let (i, indent, item_column) = found?;
+        position = Some((i + 1, indent + 1));
+        parent_indent = Some(indent);
+        match item_column {
+            Some(item_column) => {
+                line = i;
+                column = item_column;
+            }
+            None => {
+                line = i + 1;
+                column = 0;
+            }
+        }
+    }
+    position
+}
+
+/// Whether a line of a mapping starts with the key, which may be quoted.
+fn is_key(content: &str, key: &str) -> bool {
+    [
+        key.to_string(),
+        format!("\"{}\"", key),
+        format!("'{}'", key),
+    ]
+    .iter()
+    .any(|quoted| {
+        content
+            .strip_prefix(quoted.as_str())
+            .and_then(|rest| rest.strip_prefix(':'))
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+    })
+}
+
+fn parse_problem(file: Option<String>, err: serde_yaml::Error) -> ValidationProblem {
+    let mut message = err.to_string();
+    let location = match err.location() {
+        Some(location) => {
+            // The location is reported separately.
+            let suffix = format!(" at line {} column {}", location.line(), location.column());
+            if let Some(stripped) = message.strip_suffix(&suffix) {
+                message = stripped.to_string();
+            }
+            ProblemLocation::Position {
+                line: location.line(),
+                column: location.column(),
+            }
+        }
+        None => ProblemLocation::File,
+    };
+    ValidationProblem {
+        file,
+        location,
+        message,
+    }
+}
+
+fn check_products(config: &ProductsConfig, ats: &ArtifactTypes) -> Vec<ValidationProblem> {
+    let mut problems = Vec::new();
+    for (product_key, product) in &config.products {
+        let mut used_keys = BTreeSet::new();
+        let version_downloads = product.versions.map().iter().flat_map(|(version, info)| {
+            info.downloads
+                .keys()
+                .map(move |key| (format!("versions.{}.downloads.{}", version, key), key))
+        });
+        let nightly_downloads = product.nightly.iter().flat_map(|nightly| {
+            nightly
+                .downloads()
+                .keys()
+                .map(|key| (format!("nightly.downloads.{}", key), key))
+        });
+        let template_downloads = product
+            .download_templates
+            .keys()
+            .map(|key| (format!("download_templates.{}", key), key));
+        let discovered_downloads = product
+            .discover
+            .iter()
+            .flatten()
+            .enumerate()
+            .map(|(i, pattern)| (format!("discover.{}.artifact", i), &pattern.artifact));
+        for (path, key) in version_downloads
+            .chain(nightly_downloads)
+            .chain(template_downloads)
+            .chain(discovered_downloads)
+        {
+            if key == FALLBACK_KEY || ats.get(key).is_none() {
+                problems.push(ValidationProblem {
+                    file: None,
+                    location: ProblemLocation::Path(format!("products.{}.{}", product_key, path)),
+                    message: format!("Unknown artifact type '{}'.", key),
+                });
+            } else {
+                used_keys.insert(key);
+            }
+        }
+        for (version, info) in product.versions.map() {
+            for (key, path, download) in download_paths(&info.downloads) {
+                if download.url() == DOWNLOAD_TEMPLATE_DEFAULT
+                    && !product.download_templates.contains_key(key)
+                {
+                    problems.push(ValidationProblem {
+                        file: None,
+                        location: ProblemLocation::Path(format!(
+                            "products.{}.versions.{}.downloads.{}",
+                            product_key, version, path
+                        )),
+                        message: format!("No download template for artifact type '{}'.", key),
+                    });
+                }
+                for (algorithm, value) in download.checksums().iter() {
+                    if !is_valid_checksum(algorithm, value) {
+                        problems.push(ValidationProblem {
+                            file: None,
+                            location: ProblemLocation::Path(format!(
+                                "products.{}.versions.{}.downloads.{}.{}",
+                                product_key, version, path, algorithm
+                            )),
+                            message: format!("Invalid {} checksum.", algorithm.to_uppercase()),
+                        });
+                    }
+                }
+            }
+        }
+        for (version, info) in product.versions.map() {
+            let dates = [
+                ("date", Some(&info.date)),
+                ("support.until", info.support.as_ref().map(|s| &s.until)),
+            ];
+            for (field, date) in dates {
+                if let Some(ReleaseDate::Unparsed(raw)) = date {
+                    problems.push(ValidationProblem {
+                        file: None,
+                        location: ProblemLocation::Path(format!(
+                            "products.{}.versions.{}.{}",
+                            product_key, version, field
+                        )),
+                        message: format!(
+                            "Unknown date format '{}', use ISO 8601 (YYYY-MM-DD).",
+                            raw
+                        ),
+                    });
+                }
+            }
+        }
+        for key in used_keys {
+            let Some(at) = ats.get(key) else { continue };
+            if let Err(message) = at.validate_setting(product.settings.get(key)) {
+                problems.push(ValidationProblem {
+                    file: None,
+                    location: ProblemLocation::Path(format!(
+                        "products.{}.settings.{}",
+                        product_key, key
+                    )),
+                    message: format!("Invalid setting for artifact type '{}': {}", key, message),
+                });
+            }
+        }
+    }
+    check_aliases(config, &mut problems);
+    problems
+}
+
+/// Reports aliases that match the key or an alias of another product, or the name or an alias
+/// of another version of the same product. Keys and aliases are matched ignoring case.
+fn check_aliases(config: &ProductsConfig, problems: &mut Vec<ValidationProblem>) {
+    let products = config
+        .products
+        .iter()
+        .map(|(key, product)| (key, &product.aliases));
+    check_alias_conflicts(products, "products", "product", problems);
+    for (product_key, product) in &config.products {
+        let versions = product
+            .versions
+            .map()
+            .iter()
+            .map(|(name, info)| (name, &info.aliases));
+        let path = format!("products.{}.versions", product_key);
+        check_alias_conflicts(versions, &path, "version", problems);
+    }
+}
+
+fn check_alias_conflicts<'a>(
+    entries: impl Iterator<Item = (&'a String, &'a Vec<String>)> + Clone,
+    path: &str,
+    kind: &str,
+    problems: &mut Vec<ValidationProblem>,
+) {
+    let mut names: HashMap<String, &str> = entries
+        .clone()
+        .map(|(key, _)| (key.to_lowercase(), key.as_str()))
+        .collect();
+    for (key, aliases) in entries {
+        for (i, alias) in aliases.iter().enumerate() {
+            match names.entry(alias.to_lowercase()) {
+                Entry::Occupied(owner) if *owner.get() != key => {
+                    problems.push(ValidationProblem {
+                        file: None,
+                        location: ProblemLocation::Path(format!("{}.{}.aliases.{}", path, key, i)),
+                        message: format!(
+                            "Alias '{}' is already used by {} '{}'.",
+                            alias,
+                            kind,
+                            owner.get()
+                        ),
+                    });
+                }
+                Entry::Occupied(_) => {}
+                Entry::Vacant(vacant) => {
+                    vacant.insert(key);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the downloads with their path below `downloads`: The artifact key, followed by the
+/// index in the list if the artifact type has more than one download.
+fn download_paths(
+    downloads: &IndexMap<ArtifactKey, Vec<DownloadSpec>>,
+) -> Vec<(&ArtifactKey, String, &DownloadSpec)> {
+    downloads
+        .iter()
+        .flat_map(|(key, downloads)| {
+            downloads.iter().enumerate().map(move |(i, download)| {
+                let path = match downloads.len() {
+                    1 => key.clone(),
+                    _ => format!("{}.{}", key, i),
+                };
+                (key, path, download)
+            })
+        })
+        .collect()
+}
+
+/// Number of files checked at the same time on each endpoint.
+const CONCURRENT_CHECKS: usize = 16;
+
+/// Availability of the files referenced in a product configuration.
+#[derive(Debug, Default, Serialize)]
+pub struct AvailabilityReport {
+    /// Results by endpoint key.
+    pub endpoints: IndexMap<String, FileAvailability>,
+    /// Results for the banner files, which are not stored on the endpoints.
+    pub banner: FileAvailability,
+}
+
+impl AvailabilityReport {
+    /// Whether all files are available everywhere.
+    pub fn is_ok(&self) -> bool {
+        self.endpoints
+            .values()
+            .chain([&self.banner])
+            .all(FileAvailability::is_ok)
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct FileAvailability {
+    /// Number of files checked.
+    pub checked: usize,
+    /// Files that do not exist.
+    pub missing: Vec<FileReference>,
+    /// Files that could not be checked.
+    pub unreachable: Vec<UnreachableFile>,
+}
+
+impl FileAvailability {
+    fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.unreachable.is_empty()
+    }
+
+    fn add(&mut self, file: FileReference, result: Result<bool, String>) {
+        self.checked += 1;
+        match result {
+            Ok(true) => {}
+            Ok(false) => self.missing.push(file),
+            Err(error) => self.unreachable.push(UnreachableFile { file, error }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileReference {
+    /// The path of the file on the endpoint, or its URL.
+    pub file: String,
+    /// The path of keys in the product configuration that references the file.
+    pub referenced_by: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnreachableFile {
+    #[serde(flatten)]
+    pub file: FileReference,
+    pub error: String,
+}
+
+/// Checks that every file referenced in the product configuration exists on every endpoint:
+/// The files and signatures of all downloads and the product icons. The banner files are checked
+/// via their URLs. Downloads that can not be resolved to a file are skipped, they are reported by
+/// `validate_products`.
+pub async fn check_availability(
+    config: &ProductsConfig,
+    ats: &ArtifactTypes,
+    storage: &Storage,
+) -> AvailabilityReport {
+    let mut files = Vec::new();
+    for (product_key, product) in &config.products {
+        if let Some(icon_path) = &product.icon_path {
+            files.push(FileReference {
+                file: icon_path.clone(),
+                referenced_by: format!("products.{}.icon_path", product_key),
+            });
+        }
+        for (version, info) in product.versions.map() {
+            let downloads = expand_download_templates(
+                &info.downloads,
+                &product.download_templates,
+                product_key,
+                version,
+            );
+            for (key, path, download) in download_paths(&downloads) {
+                if let Some((format, signature)) = download.signature() {
+                    if !signature.contains("://") {
+                        files.push(FileReference {
+                            file: format!("{}/{}/{}", product_key, version, signature),
+                            referenced_by: format!(
+                                "products.{}.versions.{}.downloads.{}.{}",
+                                product_key, version, path, format
+                            ),
+                        });
+                    }
+                }
+                let file_path = match get_artifact_info(
+                    ats,
+                    key,
+                    product_key,
+                    version,
+                    download,
+                    product.settings.get(key),
+                )
+                .await
+                {
+                    Ok(artifact_info) => artifact_info.file_path(product_key, version),
+                    Err(_) => continue,
+                };
+                if let Some(file_path) = file_path {
+                    files.push(FileReference {
+                        file: file_path,
+                        referenced_by: format!(
+                            "products.{}.versions.{}.downloads.{}",
+                            product_key, version, path
+                        ),
+                    });
+                }
+            }
+        }
+    }
+
+    let mut report = AvailabilityReport::default();
+    for endpoint in storage.endpoints().get_all() {
+        let Some(backend) = storage.backend(&endpoint.key) else {
+            continue;
+        };
+        let checks: Vec<_> = files
+            .iter()
+            .map(|file| file_exists(backend, &file.file))
+            .collect();
+        let results: Vec<_> = stream::iter(checks)
+            .buffered(CONCURRENT_CHECKS)
+            .collect()
+            .await;
+        let availability = report.endpoints.entry(endpoint.key.clone()).or_default();
+        for (file, result) in files.iter().zip(results) {
+            availability.add(file.clone(), result);
+        }
+    }
+
+    if let Some(banner) = &config.banner {
+        for (url, field) in [
+            (&banner.url_file, "url_file"),
+            (&banner.image_file, "image_file"),
+        ] {
+            let result = match reqwest::Client::new().head(url).send().await {
+                Ok(response) if response.status() == StatusCode::NOT_FOUND => Ok(false),
+                Ok(response) => response
+                    .error_for_status()
+                    .map(|_| true)
+                    .map_err(|err| err.to_string()),
+                Err(err) => Err(err.to_string()),
+            };
+            report.banner.add(
+                FileReference {
+                    file: url.clone(),
+                    referenced_by: format!("banner.{}", field),
+                },
+                result,
+            );
+        }
+    }
+    report
+}
+
+async fn file_exists(backend: &dyn StorageBackend, key: &str) -> Result<bool, String> {
+    match backend.stat_object(key).await {
+        Ok(object) => Ok(object.is_some()),
+        Err(err) => Err(err.to_string()),
+    }
+}
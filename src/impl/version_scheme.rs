@@ -0,0 +1,308 @@
+use std::cmp::Ordering;
+
+use serde::Deserialize;
+
+use crate::r#impl::storage::VersionInfo;
+
+/// How the versions of a product are ordered. The order decides which version is the latest
+/// version, the previous and next versions of a release and the order of the releases list.
+///
+/// The default is `Manifest`, or `Semver` with the deprecated `sort_versions` feature.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VersionScheme {
+    /// In the order of the configuration, oldest first.
+    #[cfg_attr(not(feature = "sort_versions"), default)]
+    Manifest,
+    /// By version number, following the precedence rules of Semantic Versioning: Numeric parts
+    /// are compared as numbers and pre-releases (`1.0.0-rc.1`, also `1.0.0rc1`) come before the
+    /// release. A leading `v` and build metadata (`+...`) are ignored.
+    #[cfg_attr(feature = "sort_versions", default)]
+    Semver,
+    /// By calendar version (`2024.05`, `24.04.1`, `2024-05-01`): Parts separated by `.`, `-` or
+    /// `_` are compared as numbers, versions with a suffix (`2024.05-rc1`) come before the
+    /// version without it.
+    Calver,
+    /// By release date. Versions with the same date (or without a date) keep the order of the
+    /// configuration, versions without a date come first.
+    Date,
+}
+
+impl VersionScheme {
+    /// Compares two versions, older versions are less. Under `Manifest` all versions are equal,
+    /// so a stable sort keeps the order of the configuration.
+    pub fn compare(
+        &self,
+        (a_name, a_info): (&str, &VersionInfo),
+        (b_name, b_info): (&str, &VersionInfo),
+    ) -> Ordering {
+        match self {
+            VersionScheme::Manifest => Ordering::Equal,
+            VersionScheme::Semver => compare_semver(a_name, b_name),
+            VersionScheme::Calver => compare_calver(a_name, b_name),
+            VersionScheme::Date => a_info.date.date_time().cmp(&b_info.date.date_time()),
+        }
+    }
+}
+
+fn compare_semver(a: &str, b: &str) -> Ordering {
+    let (a_core, a_pre) = split_semver(a);
+    let (b_core, b_pre) = split_semver(b);
+    compare_numbers(&a_core, &b_core).then_with(|| compare_pre_release(a_pre, b_pre))
+}
+
+/// Splits a version into its numeric parts and its pre-release, if any.
+fn split_semver(version: &str) -> (Vec<u64>, Option<&str>) {
+    let version = version.strip_prefix(['v', 'V']).unwrap_or(version);
+    let version = version
+        .split_once('+')
+        .map_or(version, |(version, _)| version);
+    let mut core = Vec::new();
+    let mut rest = version;
+    loop {
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits == 0 {
+            break;
+        }
+        core.push(rest[..digits].parse().unwrap_or(u64::MAX));
+        rest = &rest[digits..];
+        match rest.strip_prefix('.') {
+            Some(next) if next.starts_with(|c: char| c.is_ascii_digit()) => rest = next,
+            _ => break,
+        }
+    }
+    let pre_release = rest.trim_start_matches(['-', '.', '_']);
+    (core, (!pre_release.is_empty()).then_some(pre_release))
+}
+
+/// Versions without a pre-release come after versions with one. Pre-releases are compared by
+/// their dot-separated identifiers.
+fn compare_pre_release(a: Option<&str>, b: Option<&str>) -> Ordering {
+    match (a, b) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => {
+            let mut a = a.split('.');
+            let mut b = b.split('.');
+            loop {
+                match (a.next(), b.next()) {
+                    (None, None) => return Ordering::Equal,
+                    (None, Some(_)) => return Ordering::Less,
+                    (Some(_), None) => return Ordering::Greater,
+                    (Some(a), Some(b)) => match compare_identifier(a, b) {
+                        Ordering::Equal => continue,
+                        ordering => return ordering,
+                    },
+                }
+            }
+        }
+    }
+}
+
+/// Numeric identifiers are compared as numbers and come before alphanumeric identifiers.
+/// Alphanumeric identifiers are compared with the numbers in them as numbers, so `rc10` comes
+/// after `rc9`.
+fn compare_identifier(a: &str, b: &str) -> Ordering {
+    match (a.parse::<u64>(), b.parse::<u64>()) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => compare_natural(a, b),
+    }
+}
+
+fn compare_calver(a: &str, b: &str) -> Ordering {
+    let (a_parts, a_suffix) = split_calver(a);
+    let (b_parts, b_suffix) = split_calver(b);
+    compare_numbers(&a_parts, &b_parts).then_with(|| match (a_suffix, b_suffix) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => compare_natural(a, b),
+    })
+}
+
+/// Splits a version into its numeric parts and the suffix after them, if any.
+fn split_calver(version: &str) -> (Vec<u64>, Option<&str>) {
+    let version = version.strip_prefix(['v', 'V']).unwrap_or(version);
+    let mut parts = Vec::new();
+    let mut rest = version;
+    while let Some((part, next)) = rest
+        .split_once(['.', '-', '_'])
+        .or(Some((rest, "")))
+        .filter(|(part, _)| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+    {
+        parts.push(part.parse().unwrap_or(u64::MAX));
+        rest = next;
+    }
+    (parts, (!rest.is_empty()).then_some(rest))
+}
+
+/// Compares numeric parts, missing parts count as 0 (`1.0` is `1.0.0`).
+fn compare_numbers(a: &[u64], b: &[u64]) -> Ordering {
+    let len = a.len().max(b.len());
+    (0..len)
+        .map(|i| {
+            let a = a.get(i).copied().unwrap_or(0);
+            let b = b.get(i).copied().unwrap_or(0);
+            a.cmp(&b)
+        })
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Compares strings with the runs of digits in them compared as numbers.
+fn compare_natural(a: &str, b: &str) -> Ordering {
+    let mut a = a;
+    let mut b = b;
+    loop {
+        let (a_run, a_rest) = split_run(a);
+        let (b_run, b_rest) = split_run(b);
+        let ordering = match (a_run.parse::<u64>(), b_run.parse::<u64>()) {
+            (Ok(a_number), Ok(b_number)) => a_number.cmp(&b_number),
+            _ => a_run.cmp(b_run),
+        };
+        if ordering.is_ne() || (a_rest.is_empty() && b_rest.is_empty()) {
+            return ordering.then_with(|| a_rest.cmp(b_rest));
+        }
+        a = a_rest;
+        b = b_rest;
+    }
+}
+
+/// Splits off the leading run of digits or of other characters.
+fn split_run(s: &str) -> (&str, &str) {
+    let is_digit = s.starts_with(|c: char| c.is_ascii_digit());
+    let end = s
+        .find(|c: char| c.is_ascii_digit() != is_digit)
+        .unwrap_or(s.len());
+    s.split_at(end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r#impl::release_date::ReleaseDate;
+    use crate::r#impl::release_map::ReleaseMap;
+
+    fn sorted(scheme: VersionScheme, names: &[&str]) -> Vec<String> {
+        let mut versions: ReleaseMap = names
+            .iter()
+            .map(|name| (name.to_string(), VersionInfo::default()))
+            .collect();
+        versions.sort(scheme);
+        versions.map().keys().cloned().collect()
+    }
+
+    #[test]
+    fn semver_pre_release_before_release() {
+        assert_eq!(compare_semver("1.0.0-rc.1", "1.0.0"), Ordering::Less);
+        assert_eq!(compare_semver("1.0.0rc1", "1.0.0"), Ordering::Less);
+        assert_eq!(compare_semver("1.0.0-rc.1", "0.9.0"), Ordering::Greater);
+        assert_eq!(compare_semver("1.0.0-alpha", "1.0.0-beta"), Ordering::Less);
+        assert_eq!(compare_semver("1.0.0-rc.2", "1.0.0-rc.10"), Ordering::Less);
+        assert_eq!(compare_semver("1.0.0-rc9", "1.0.0-rc10"), Ordering::Less);
+        assert_eq!(
+            compare_semver("1.0.0-alpha", "1.0.0-alpha.1"),
+            Ordering::Less
+        );
+        assert_eq!(compare_semver("1.0.0-1", "1.0.0-alpha"), Ordering::Less);
+    }
+
+    #[test]
+    fn semver_compares_numbers() {
+        assert_eq!(compare_semver("1.2.0", "1.10.0"), Ordering::Less);
+        assert_eq!(compare_semver("v2.0.0", "1.99.99"), Ordering::Greater);
+        assert_eq!(compare_semver("1.0", "1.0.0"), Ordering::Equal);
+        assert_eq!(compare_semver("v1.0.0", "1.0.0"), Ordering::Equal);
+    }
+
+    #[test]
+    fn semver_ignores_build_metadata() {
+        assert_eq!(
+            compare_semver("1.0.0+build.1", "1.0.0+build.2"),
+            Ordering::Equal
+        );
+        assert_eq!(compare_semver("1.0.0+20240101", "1.0.0"), Ordering::Equal);
+        assert_eq!(compare_semver("1.0.0-rc.1+abc", "1.0.0"), Ordering::Less);
+    }
+
+    #[test]
+    fn semver_invalid_versions() {
+        // Versions without numbers count as pre-releases of 0.
+        assert_eq!(compare_semver("nightly", "0.0.1"), Ordering::Less);
+        assert_eq!(compare_semver("abc", "abd"), Ordering::Less);
+        assert_eq!(compare_semver("", "0.1"), Ordering::Less);
+        assert_eq!(compare_semver("1.x", "1.0"), Ordering::Less);
+        // Numbers too large for u64 sort last instead of failing.
+        assert_eq!(
+            compare_semver("99999999999999999999.0", "1.0"),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn calver_compares_parts_as_numbers() {
+        assert_eq!(compare_calver("2024.1", "2024.10"), Ordering::Less);
+        assert_eq!(compare_calver("2024.2", "2024.10"), Ordering::Less);
+        assert_eq!(compare_calver("2024.05", "2024.5"), Ordering::Equal);
+        assert_eq!(compare_calver("2023.12", "2024.01"), Ordering::Less);
+        assert_eq!(compare_calver("2024-05-01", "2024.05.02"), Ordering::Less);
+    }
+
+    #[test]
+    fn calver_mixed_segment_counts() {
+        assert_eq!(compare_calver("24.04", "24.04.1"), Ordering::Less);
+        assert_eq!(compare_calver("24.04.0", "24.04"), Ordering::Equal);
+        assert_eq!(compare_calver("24.10", "24.04.1"), Ordering::Greater);
+        assert_eq!(compare_calver("2024", "2024.1"), Ordering::Less);
+    }
+
+    #[test]
+    fn calver_suffix_before_release() {
+        assert_eq!(compare_calver("2024.05-rc1", "2024.05"), Ordering::Less);
+        assert_eq!(
+            compare_calver("2024.05-rc2", "2024.05-rc10"),
+            Ordering::Less
+        );
+        assert_eq!(compare_calver("2024.05-rc1", "2024.04"), Ordering::Greater);
+    }
+
+    #[test]
+    fn date_orders_by_release_date() {
+        let dated = |date: &str| VersionInfo {
+            date: ReleaseDate::parse(date),
+            ..Default::default()
+        };
+        let (older, newer, undated) = (dated("2023-01-02"), dated("2023-02-01"), dated(""));
+        let scheme = VersionScheme::Date;
+        assert_eq!(scheme.compare(("b", &older), ("a", &newer)), Ordering::Less);
+        assert_eq!(
+            scheme.compare(("a", &undated), ("b", &older)),
+            Ordering::Less
+        );
+        assert_eq!(
+            scheme.compare(("a", &older), ("b", &older)),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn manifest_keeps_order() {
+        let names = ["2.0.0", "1.0.0", "1.0.0-rc.1", "10.0", "beta"];
+        assert_eq!(sorted(VersionScheme::Manifest, &names), names);
+    }
+
+    #[test]
+    fn sort_is_stable_for_equal_versions() {
+        assert_eq!(
+            sorted(VersionScheme::Semver, &["1.0.0", "v1.0.0", "0.1", "1.0"]),
+            ["0.1", "1.0.0", "v1.0.0", "1.0"]
+        );
+        assert_eq!(
+            sorted(VersionScheme::Calver, &["2024.10", "2024.1", "2024.2"]),
+            ["2024.1", "2024.2", "2024.10"]
+        );
+    }
+}
@@ -2,6 +2,9 @@
 /// See `main.rs` for an example on how to use the routes and config.
 mod r#impl;
 
+pub use r#impl::admin;
 pub use r#impl::artifacttype;
 pub use r#impl::config;
 pub use r#impl::routes;
+pub use r#impl::storage;
+pub use r#impl::validate;
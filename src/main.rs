@@ -2,36 +2,140 @@
 #[macro_use]
 extern crate rocket;
 
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+use rocket::fairing::AdHoc;
 use rocket::fs::FileServer;
 use rocket::{catchers, routes, Build, Rocket};
 
+use r#impl::admin::*;
 #[cfg(feature = "flatpak")]
 use r#impl::artifacttype::r#impl::flatpak::{
     get_flatpakref, get_flatpakref_beta, get_flatpakref_custom,
 };
 use r#impl::routes::*;
 
+use crate::r#impl::artifacttype::ArtifactTypes;
 use crate::r#impl::config::Config;
+use crate::r#impl::validate::{check_availability, validate_products};
 
 mod r#impl;
 
-#[launch]
-pub fn rocket() -> Rocket<Build> {
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Checks a products.yml file for problems and exits with a non-zero status if there are any.
+    Validate {
+        /// Path to the products.yml file.
+        path: PathBuf,
+        /// Also check that all referenced files are available on every configured endpoint and
+        /// print a JSON report of missing and unreachable files.
+        #[arg(long)]
+        mirrors: bool,
+    },
+}
+
+#[rocket::main]
+async fn main() -> ExitCode {
     pretty_env_logger::init_timed();
 
-    if let Ok(config) = Config::load(Default::default()) {
-        rocket::build()
+    match Cli::parse().command {
+        Some(Command::Validate { path, mirrors }) => validate(path, mirrors).await,
+        None => match rocket().launch().await {
+            Ok(_) => ExitCode::SUCCESS,
+            Err(err) => {
+                error!("{}", err);
+                ExitCode::FAILURE
+            }
+        },
+    }
+}
+
+async fn validate(path: PathBuf, mirrors: bool) -> ExitCode {
+    let raw = match fs::read_to_string(&path) {
+        Ok(raw) => raw,
+        Err(err) => {
+            eprintln!("{}: {}", path.display(), err);
+            return ExitCode::FAILURE;
+        }
+    };
+    let ats = ArtifactTypes::default();
+    // Included files are relative to the root of the endpoint, which contains products.yml.
+    let root = path.parent().unwrap_or(Path::new("."));
+    let validation =
+        validate_products(&raw, &ats, |include| fs::read_to_string(root.join(include)));
+    for problem in &validation.problems {
+        let file = match &problem.file {
+            Some(include) => root.join(include),
+            None => path.clone(),
+        };
+        eprintln!(
+            "{}: {}: {}",
+            file.display(),
+            problem.location,
+            problem.message
+        );
+    }
+    let mut ok = if validation.problems.is_empty() {
+        eprintln!("{}: No problems found.", path.display());
+        true
+    } else {
+        eprintln!("{} problem(s) found.", validation.problems.len());
+        false
+    };
+
+    if let (true, Some(products_config)) = (mirrors, validation.config) {
+        let config = match Config::load(ats) {
+            Ok(config) => config,
+            Err(err) => {
+                eprintln!("{}", err);
+                return ExitCode::FAILURE;
+            }
+        };
+        let report =
+            check_availability(&products_config, config.artifact_types(), config.storage()).await;
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+        if !report.is_ok() {
+            eprintln!("Some files are missing or unreachable.");
+            ok = false;
+        }
+    }
+
+    if ok {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+pub fn rocket() -> Rocket<Build> {
+    match Config::load(Default::default()) {
+        Ok(config) => rocket::build()
             .mount("/", {
                 #[cfg(feature = "flatpak")]
                 {
                     routes![
                         get_root,
                         get_product,
+                        get_channel,
                         get_release_en,
                         get_release,
                         get_nightly_artifact,
                         get_banner,
                         get_banner_png,
+                        get_local_file,
+                        get_health,
+                        post_admin_refresh,
+                        get_admin_mirror_report,
                         favicon,
                         get_flatpakref,
                         get_flatpakref_beta,
@@ -43,11 +147,16 @@ pub fn rocket() -> Rocket<Build> {
                     routes![
                         get_root,
                         get_product,
+                        get_channel,
                         get_release_en,
                         get_release,
                         get_nightly_artifact,
                         get_banner,
                         get_banner_png,
+                        get_local_file,
+                        get_health,
+                        post_admin_refresh,
+                        get_admin_mirror_report,
                         favicon,
                     ]
                 }
@@ -58,7 +167,16 @@ pub fn rocket() -> Rocket<Build> {
                 catchers![not_found, internal_server_error, other_error],
             )
             .manage(config)
-    } else {
-        panic!("Could not load configuration.")
+            .attach(AdHoc::on_liftoff("Background Tasks", |rocket| {
+                Box::pin(async move {
+                    if let Some(config) = rocket.state::<Config>() {
+                        config.spawn_background_tasks();
+                    }
+                })
+            })),
+        Err(err) => {
+            error!("{}", err);
+            panic!("Could not load configuration.")
+        }
     }
 }
@@ -0,0 +1,241 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// Writes the products.yml to a directory of its own and runs `deposit-box validate` on it.
+fn validate(name: &str, products_yml: &str) -> Output {
+    validate_with_includes(name, products_yml, &[])
+}
+
+/// Like `validate`, with files for the products.yml to include, by path.
+fn validate_with_includes(name: &str, products_yml: &str, includes: &[(&str, &str)]) -> Output {
+    let dir: PathBuf = [env!("CARGO_TARGET_TMPDIR"), name].iter().collect();
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    for (include, content) in includes {
+        let include = dir.join(include);
+        fs::create_dir_all(include.parent().unwrap()).unwrap();
+        fs::write(include, content).unwrap();
+    }
+    let path = dir.join("products.yml");
+    fs::write(&path, products_yml).unwrap();
+    Command::new(env!("CARGO_BIN_EXE_deposit-box"))
+        .arg("validate")
+        .arg(&path)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn valid_config_passes() {
+    let output = validate(
+        "valid",
+        r#"
+products:
+  tool:
+    name: Tool
+    versions:
+      1.0.0:
+        date: "2023-01-02"
+"#,
+    );
+    assert!(output.status.success());
+}
+
+#[test]
+fn misspelled_versions_fails() {
+    let output = validate(
+        "misspelled-versions",
+        r#"
+products:
+  tool:
+    name: Tool
+    versoins:
+      1.0.0:
+        date: "2023-01-02"
+"#,
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("missing field `versions`"), "{}", stderr);
+}
+
+#[test]
+fn unknown_keys_fail_with_position() {
+    let output = validate(
+        "unknown-keys",
+        r#"
+products:
+  tool:
+    name: Tool
+    discover: []
+    versoins: {}
+    versions:
+      1.0.0:
+        date: "2023-01-02"
+        descripton: Typo
+        downloads:
+          win64:
+            url: tool.exe
+            sha265: abc
+"#,
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("6:5: Unknown key 'versoins' in products.tool."),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("10:9: Unknown key 'descripton' in products.tool.versions.1.0.0."),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains(
+            "14:13: Unknown key 'sha265' in products.tool.versions.1.0.0.downloads.win64.0."
+        ),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn null_download_suppresses_template() {
+    let output = validate(
+        "null-download",
+        r#"
+products:
+  tool:
+    name: Tool
+    download_templates:
+      win64: "{product}-{version}-win64.exe"
+      mac64: "{product}-{version}.dmg"
+    versions:
+      1.0.0:
+        date: "2023-01-02"
+        downloads:
+          mac64: ~
+"#,
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn included_products_pass() {
+    let output = validate_with_includes(
+        "include",
+        "products:\n  tool:\n    include: products/tool.yml\n",
+        &[(
+            "products/tool.yml",
+            "name: Tool\nversions:\n  1.0.0:\n    date: \"2023-01-02\"\n",
+        )],
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn missing_included_file_fails() {
+    let output = validate(
+        "include-missing",
+        "products:\n  tool:\n    include: products/tool.yml\n",
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("products/tool.yml: -: Could not read included file"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn nested_include_fails() {
+    let output = validate_with_includes(
+        "include-nested",
+        "products:\n  tool:\n    include: tool.yml\n",
+        &[
+            ("tool.yml", "include: other.yml\n"),
+            ("other.yml", "name: Tool\nversions: {}\n"),
+        ],
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("included files can not include other files"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn unknown_keys_in_included_file_fail() {
+    let output = validate_with_includes(
+        "include-unknown-keys",
+        "products:\n  tool:\n    include: tool.yml\n",
+        &[("tool.yml", "name: Tool\ndescripton: Typo\nversions: {}\n")],
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("tool.yml: 2:1: Unknown key 'descripton'"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn version_without_date_fails() {
+    let output = validate(
+        "missing-date",
+        r#"
+products:
+  tool:
+    name: Tool
+    versions:
+      1.0.0:
+        date: "2023-01-02"
+      1.1.0:
+        dtae: "2023-02-03"
+"#,
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("missing field `date` in version 1.1.0"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn discovered_version_without_date_passes() {
+    let output = validate(
+        "discovered-missing-date",
+        r#"
+products:
+  tool:
+    name: Tool
+    discover:
+      - pattern: "tool-.*\\.exe"
+        artifact: win64
+    versions:
+      1.0.0:
+        description: Discovered, with the date of its files.
+"#,
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
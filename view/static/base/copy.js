@@ -0,0 +1,19 @@
+// Copy buttons: Copies the value of the button's data-copy attribute to the clipboard.
+// The buttons are hidden if the browser doesn't support it.
+document.addEventListener("DOMContentLoaded", function () {
+    if (!navigator.clipboard) {
+        return;
+    }
+    document.querySelectorAll("button[data-copy]").forEach(function (el) {
+        el.hidden = false;
+        el.onclick = function () {
+            navigator.clipboard.writeText(el.dataset.copy).then(function () {
+                var text = el.textContent;
+                el.textContent = "Copied";
+                setTimeout(function () {
+                    el.textContent = text;
+                }, 1500);
+            });
+        };
+    });
+});
@@ -0,0 +1,48 @@
+// Renders <time data-local-time> elements in the visitor's locale. The server renders them in
+// English (UTC), which is kept if the browser can't format dates.
+// With data-local-time="relative" the text is the time relative to now ("3 weeks ago") and the
+// full date is shown on hover. Otherwise the text is the full date.
+document.addEventListener("DOMContentLoaded", function () {
+    if (!window.Intl || !Intl.RelativeTimeFormat) {
+        return;
+    }
+    var units = [
+        ["year", 365 * 24 * 3600],
+        ["month", 30 * 24 * 3600],
+        ["week", 7 * 24 * 3600],
+        ["day", 24 * 3600],
+        ["hour", 3600],
+        ["minute", 60],
+        ["second", 1]
+    ];
+    var relativeFormat = new Intl.RelativeTimeFormat(undefined, {numeric: "auto"});
+    document.querySelectorAll("time[data-local-time]").forEach(function (el) {
+        var value = el.getAttribute("datetime");
+        var date = new Date(value);
+        if (isNaN(date)) {
+            return;
+        }
+        // Dates without a time are days, not midnight UTC.
+        var hasTime = value.indexOf("T") !== -1;
+        var full = hasTime
+            ? date.toLocaleString(undefined, {dateStyle: "long", timeStyle: "short"})
+            : date.toLocaleDateString(undefined, {dateStyle: "long", timeZone: "UTC"});
+        if (el.dataset.localTime !== "relative") {
+            el.textContent = full;
+            return;
+        }
+        el.title = full;
+        var seconds = (date.getTime() - Date.now()) / 1000;
+        for (var i = 0; i < units.length; i++) {
+            var unit = units[i];
+            if (Math.abs(seconds) >= unit[1] || unit[0] === "second") {
+                if (!hasTime && unit[1] < 24 * 3600) {
+                    el.textContent = relativeFormat.format(0, "day");
+                } else {
+                    el.textContent = relativeFormat.format(Math.round(seconds / unit[1]), unit[0]);
+                }
+                return;
+            }
+        }
+    });
+});
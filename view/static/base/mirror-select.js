@@ -1,11 +1,32 @@
 document.querySelector('.mirror-select').style.display = "block";
 
+function hasMirrorLinks(el) {
+    return Object.keys(el.dataset).some(function (key) {
+        return key.indexOf("href") === 0;
+    });
+}
+
 var mirorSelect = document.getElementById('mirror-select');
 mirorSelect.onchange = function() {
     var selected = mirorSelect.value.toLowerCase();
-    var dataAttribName = "data-href-" + selected;
-    var dataAttribNameDataset = "href" + selected.charAt(0).toUpperCase() + selected.slice(1);
-    document.querySelectorAll("[" + dataAttribName + "]").forEach(function (el) {
-        el.href = el.dataset[dataAttribNameDataset];
+    var datasetSuffix = selected.charAt(0).toUpperCase() + selected.slice(1);
+    var dataAttribNameDataset = "href" + datasetSuffix;
+    document.querySelectorAll("[data-mirror-link]").forEach(function (el) {
+        if (dataAttribNameDataset in el.dataset) {
+            el.href = el.dataset[dataAttribNameDataset];
+            el.classList.remove("missing-on-mirror");
+            el.removeAttribute("title");
+        } else if (hasMirrorLinks(el)) {
+            // Only files that are on other mirrors are missing on this one.
+            el.classList.add("missing-on-mirror");
+            el.title = "This file is not available on the selected mirror.";
+        }
+    });
+    var valueAttribNameDataset = "value" + datasetSuffix;
+    document.querySelectorAll("[data-mirror-value]").forEach(function (el) {
+        if (valueAttribNameDataset in el.dataset) {
+            el.textContent = el.dataset[valueAttribNameDataset];
+        }
     });
 }
+mirorSelect.onchange();